@@ -1,25 +1,115 @@
 use crate::template::variables::HasVariables;
 use axum::http::uri::PathAndQuery;
-use axum::http::{Method, StatusCode, Uri};
+use axum::http::{HeaderName, HeaderValue, Method, StatusCode, Uri};
 use handlebars::Handlebars;
+use jsonschema::Validator;
+use rand::distributions::{Uniform, WeightedIndex};
+use rand::rngs::StdRng;
+use rand_distr::{LogNormal, Normal, Pareto};
+use regex::Regex;
+use serde_json::Value;
+use serde_json_path::JsonPath;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone, Debug)]
 pub enum LatencyCore {
     Constant(u32),
+    Random {
+        distribution: LatencyDistributionCore,
+        random: RandomCore,
+    },
+}
+
+// Latency distributions in milliseconds
+#[derive(Clone, Debug)]
+pub enum LatencyDistributionCore {
+    Uniform(Uniform<f64>),
+    Normal(Normal<f64>),
+    LogNormal(LogNormal<f64>),
+    Pareto(Pareto<f64>),
+    // (quantile, latency) points, linearly interpolated
+    Percentiles(Vec<(f64, f64)>),
+}
+
+// Random source, a seeded source is shared by every request so a run can be reproduced
+#[derive(Clone, Debug)]
+pub enum RandomCore {
+    Thread,
+    Seeded(Arc<Mutex<StdRng>>),
+}
+
+#[derive(Clone, Debug)]
+pub struct ThrottleCore {
+    pub throughput: Option<u32>,
+    pub chunk_size: usize,
+    pub chunk_delay: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum FaultCore {
+    CloseConnection,
+    ResetMidBody,
+    MalformedHttp,
+    GarbageBytes,
+    Hang,
+    TruncatedContentLength,
+}
+
+// Chaos faults are drawn with a single roll in [0, 100), each fault owning a slice of that range
+#[derive(Clone, Debug)]
+pub struct ChaosCore {
+    pub faults: Vec<(f64, ChaosEffectCore)>,
+    pub random: RandomCore,
+}
+
+#[derive(Clone, Debug)]
+pub enum ChaosEffectCore {
+    Status(StatusCode),
+    Timeout(u32),
+    Fault(FaultCore),
+}
+
+#[derive(Clone, Debug)]
+pub struct VerificationCore {
+    pub method: Option<Method>,
+    pub path: Option<MatcherCore>,
+    pub headers: HashMap<String, MatcherCore>,
+    pub body_matchers: Vec<BodyMatcherCore>,
+    pub count: Option<usize>,
 }
 
 #[derive(Clone, Debug)]
 pub struct ApiCore(pub Vec<RuleCore>);
 
 #[derive(Clone, Debug)]
-pub struct ProxyCore(pub Uri);
+pub struct ProxyCore {
+    pub url: Uri,
+    // Folder where the proxied requests and responses are recorded
+    pub record: Option<PathBuf>,
+    // Unmatched requests of the static routes of the api set are forwarded to the url
+    pub fallback: bool,
+    pub request_headers: HeaderRulesCore,
+    pub response_headers: HeaderRulesCore,
+    // Shared by the requests forwarded to the url, keeping their connections pooled
+    pub client: reqwest::Client,
+}
+
+// Changes applied to the headers going through the proxy: removals, then rewrites, then additions
+#[derive(Clone, Debug, Default)]
+pub struct HeaderRulesCore {
+    pub add: Vec<(HeaderName, HeaderValue)>,
+    pub remove: Vec<HeaderName>,
+    pub rewrite: Vec<(HeaderName, Regex, String)>,
+}
 
 #[derive(Clone, Debug)]
 pub struct ApiSetCore {
     pub name: String,
+    #[allow(dead_code)]
     pub shape: Option<Vec<EndpointCore>>,
     pub apis: Vec<ApiCore>,
     pub proxy: Option<ProxyCore>,
@@ -27,16 +117,34 @@ pub struct ApiSetCore {
 
 #[derive(Clone, Debug)]
 pub struct ApiSetRootCore {
+    #[allow(dead_code)]
     pub shape: Option<Vec<EndpointCore>>,
     pub apis: Vec<ApiCore>,
+    #[allow(dead_code)]
     pub proxy: Option<ProxyCore>,
 }
 
 #[derive(Clone, Debug)]
-pub struct RuleContentFeaturesCore {
-    pub headers: bool,
-    pub url_path: bool,
-    pub url_query: bool,
+pub enum MatcherCore {
+    Exact(String),
+    Regex(Regex),
+    Prefix(String),
+    Present,
+    Absent,
+    Not(Box<MatcherCore>),
+}
+
+#[derive(Clone, Debug)]
+pub enum BodyMatcherCore {
+    Json(Value),
+    JsonPartial(Value),
+    JsonPath(JsonPath),
+    // Query and namespaces of the prefixes it uses
+    XPath {
+        query: String,
+        namespaces: HashMap<String, String>,
+    },
+    Regex(Regex),
 }
 
 #[derive(Clone, Debug)]
@@ -48,17 +156,91 @@ pub enum RuleBodyCore {
     },
 }
 
+#[derive(Clone, Debug)]
+pub struct ScenarioCore {
+    pub name: String,
+    pub required_state: Option<String>,
+    pub new_state: Option<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct RuleCore {
+    // Name of the api declaring the rule, and path of its file
+    pub api: String,
+    pub source: Option<String>,
     pub endpoint: EndpointCore,
-    pub headers: HashMap<String, String>,
+    pub headers: HashMap<String, MatcherCore>,
+    pub cookies: HashMap<String, MatcherCore>,
+    pub host: Option<MatcherCore>,
+    pub query: HashMap<String, MatcherCore>,
+    pub body_matchers: Vec<BodyMatcherCore>,
+    pub scenario: Option<ScenarioCore>,
     pub latency: Option<LatencyCore>,
+    pub throttle: Option<ThrottleCore>,
+    pub chaos: Option<ChaosCore>,
+    pub priority: i32,
+    // Schema of the request body, checked before responding when the shape validates requests
+    pub request_schema: Option<RequestSchemaCore>,
+    pub response: RuleResponseCore,
+}
+
+#[derive(Clone, Debug)]
+pub enum RuleResponseCore {
+    Single(Box<ResponseCore>),
+    // The position in the sequence is kept in the router state, under the sequence id
+    Sequence {
+        // Api set, api, endpoint and occurrence of the endpoint in the api, stable across reloads of
+        // the configuration and changes to the other rules
+        id: String,
+        mode: SequenceModeCore,
+        responses: Vec<ResponseCore>,
+    },
+    // Drawn with the random source of the router state
+    OneOf {
+        responses: Vec<ResponseCore>,
+        weights: WeightedIndex<u32>,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum SequenceModeCore {
+    Once,
+    Cycle,
+    StickLast,
+}
+
+#[derive(Clone, Debug)]
+pub struct ResponseCore {
     pub status: StatusCode,
     pub format: String,
+    pub headers: Vec<(HeaderName, RuleBodyCore)>,
     pub body: Option<RuleBodyCore>,
+    pub fault: Option<FaultCore>,
+}
+
+// Json schema of the request body of an operation, and whether the body is required
+#[derive(Clone, Debug)]
+pub struct RequestSchemaCore {
+    pub validator: Arc<Validator>,
+    pub required: bool,
+}
+
+// Json schemas of an operation of an openapi document, the response schemas being keyed by status
+// like '200', '2XX' or 'default'
+#[derive(Clone, Debug)]
+pub struct OperationContractCore {
+    pub endpoint: EndpointCore,
+    pub request: Option<RequestSchemaCore>,
+    pub responses: HashMap<String, Arc<Validator>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ContractCore {
+    pub operations: Vec<OperationContractCore>,
+    pub validate_requests: bool,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct EndpointCore {
     pub route: PathAndQuery,
     pub method: Method,
@@ -67,6 +249,7 @@ pub struct EndpointCore {
 #[derive(Clone, Debug)]
 pub struct SystemCore {
     pub name: String,
+    pub hosts: Vec<String>,
     pub root_api_set: ApiSetRootCore,
     pub api_sets: Vec<ApiSetCore>,
 }
@@ -87,3 +270,52 @@ impl Display for EndpointCore {
         write!(f, "{} -> {}", self.method, self.route.path())
     }
 }
+
+impl Display for LatencyCore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LatencyCore::Constant(millis) => write!(f, "{millis}ms"),
+            LatencyCore::Random { distribution, .. } => match distribution {
+                LatencyDistributionCore::Uniform(_) => write!(f, "uniform"),
+                LatencyDistributionCore::Normal(_) => write!(f, "normal"),
+                LatencyDistributionCore::LogNormal(_) => write!(f, "log-normal"),
+                LatencyDistributionCore::Pareto(_) => write!(f, "pareto"),
+                LatencyDistributionCore::Percentiles(_) => write!(f, "percentiles"),
+            },
+        }
+    }
+}
+
+impl Display for MatcherCore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MatcherCore::Exact(value) => write!(f, "equal to '{value}'"),
+            MatcherCore::Regex(regex) => write!(f, "matching regex '{regex}'"),
+            MatcherCore::Prefix(prefix) => write!(f, "prefixed with '{prefix}'"),
+            MatcherCore::Present => write!(f, "present"),
+            MatcherCore::Absent => write!(f, "absent"),
+            MatcherCore::Not(matcher) => write!(f, "not {matcher}"),
+        }
+    }
+}
+
+impl Display for BodyMatcherCore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyMatcherCore::Json(value) => write!(f, "json equal to '{value}'"),
+            BodyMatcherCore::JsonPartial(value) => write!(f, "json containing '{value}'"),
+            BodyMatcherCore::JsonPath(path) => write!(f, "json selected by jsonpath '{path}'"),
+            BodyMatcherCore::XPath { query, .. } => write!(f, "xml selected by xpath '{query}'"),
+            BodyMatcherCore::Regex(regex) => write!(f, "matching regex '{regex}'"),
+        }
+    }
+}
+
+impl Display for ScenarioCore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.required_state {
+            Some(state) => write!(f, "scenario '{}' in state '{state}'", self.name),
+            None => write!(f, "scenario '{}'", self.name),
+        }
+    }
+}
@@ -0,0 +1,417 @@
+use crate::core::{ApiCore, ConfCore, SystemCore};
+use crate::http::journal::JournalFilter;
+use crate::http::routes::MochiRouterState;
+use crate::yaml::to_domain::extract_verification;
+use crate::yaml::{ApiFolder, ApiYaml, ConfFolder, RuleYaml, SystemFolder, VerificationYaml};
+use anyhow::Result;
+use axum::extract::{Path, Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Json, Router};
+use itertools::Itertools;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+impl SystemCore {
+    // Names of the scenarios declared by the rules of the system
+    fn scenario_names(&self) -> Vec<String> {
+        self.root_api_set
+            .apis
+            .iter()
+            .chain(self.api_sets.iter().flat_map(|api_set| api_set.apis.iter()))
+            .flat_map(|ApiCore(rules)| rules.iter())
+            .filter_map(|rule| rule.scenario.as_ref().map(|s| s.name.to_owned()))
+            .unique()
+            .sorted()
+            .collect()
+    }
+}
+
+type DeclaredScenarios = Arc<HashMap<String, Vec<String>>>;
+
+fn not_found(system: &str, scenario: Option<&str>) -> Response {
+    let message = match scenario {
+        Some(scenario) => format!("Unknown scenario '{scenario}' in system '{system}'"),
+        None => format!("Unknown system '{system}'"),
+    };
+    (StatusCode::NOT_FOUND, message).into_response()
+}
+
+// Apis and rules are sent with the schema of the api files, in yaml or in json with the
+// application/json content type (tagged values being written as single key objects)
+fn decode<T: DeserializeOwned>(headers: &HeaderMap, body: &str, kind: &str) -> Result<T, String> {
+    let is_json = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    let decoded = if is_json {
+        serde_json::from_str(body).map_err(|e| e.to_string())
+    } else {
+        serde_yaml::from_str(body).map_err(|e| e.to_string())
+    };
+
+    decoded.map_err(|e| format!("Could not decode {kind}: {e}"))
+}
+
+// Apis are managed in the root api set of the system, or in the api set given as query parameter
+#[derive(Deserialize)]
+struct ApiSetQuery {
+    api_set: Option<String>,
+}
+
+impl ApiSetQuery {
+    fn apis<'a>(&self, system: &'a mut SystemFolder) -> Option<&'a mut Vec<ApiYaml>> {
+        match &self.api_set {
+            Some(name) => system
+                .api_folders
+                .iter_mut()
+                .find(|f| &f.name == name)
+                .map(|f| &mut f.apis),
+            None => Some(&mut system.apis),
+        }
+    }
+
+    fn find_api<'a>(
+        &self,
+        conf: &'a mut ConfFolder,
+        system: &str,
+        api: &str,
+    ) -> Option<&'a mut ApiYaml> {
+        let system = conf.systems.iter_mut().find(|s| s.name == system)?;
+        self.apis(system)?.iter_mut().find(|a| a.name == api)
+    }
+
+    fn describe(&self, system: &str) -> String {
+        match &self.api_set {
+            Some(name) => format!("api set '{name}' of system '{system}'"),
+            None => format!("system '{system}'"),
+        }
+    }
+}
+
+fn conf_change_response(outcome: Result<Option<StatusCode>>, missing: String) -> Response {
+    match outcome {
+        Ok(Some(status)) => status.into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, missing).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid configuration, the previous one is kept: {e:?}"),
+        )
+            .into_response(),
+    }
+}
+
+async fn list_apis(
+    State(s): State<MochiRouterState>,
+    Path(system): Path<String>,
+    Query(api_set): Query<ApiSetQuery>,
+) -> Response {
+    let mut conf = s.conf();
+    match conf
+        .systems
+        .iter_mut()
+        .find(|s| s.name == system)
+        .and_then(|system| api_set.apis(system))
+    {
+        Some(apis) => Json(apis.iter().map(|a| &a.name).collect_vec()).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("Unknown {}", api_set.describe(&system)),
+        )
+            .into_response(),
+    }
+}
+
+async fn put_api(
+    State(s): State<MochiRouterState>,
+    Path((system, name)): Path<(String, String)>,
+    Query(api_set): Query<ApiSetQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let api = match decode::<ApiYaml>(&headers, &body, "api") {
+        Ok(api) => ApiYaml { name, ..api },
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    // Unknown systems and api sets are created along the api
+    let outcome = s.update_conf(|conf| {
+        let position = match conf.systems.iter().position(|s| s.name == system) {
+            Some(position) => position,
+            None => {
+                conf.systems.push(SystemFolder {
+                    name: system.to_owned(),
+                    host: None,
+                    api_folders: vec![],
+                    shape: None,
+                    proxy: None,
+                    apis: vec![],
+                    data: HashMap::new(),
+                });
+                conf.systems.len() - 1
+            }
+        };
+        let system = &mut conf.systems[position];
+        if let Some(name) = api_set.api_set.as_ref() {
+            if !system.api_folders.iter().any(|f| &f.name == name) {
+                system.api_folders.push(ApiFolder {
+                    name: name.to_owned(),
+                    proxy: None,
+                    shape: None,
+                    apis: vec![],
+                    data: HashMap::new(),
+                });
+            }
+        }
+        let apis = api_set.apis(system)?;
+        match apis.iter_mut().find(|a| a.name == api.name) {
+            Some(existing) => {
+                *existing = api;
+                Some(StatusCode::OK)
+            }
+            None => {
+                apis.push(api);
+                Some(StatusCode::CREATED)
+            }
+        }
+    });
+
+    conf_change_response(outcome, String::new())
+}
+
+async fn delete_api(
+    State(s): State<MochiRouterState>,
+    Path((system, name)): Path<(String, String)>,
+    Query(api_set): Query<ApiSetQuery>,
+) -> Response {
+    let outcome = s.update_conf(|conf| {
+        let apis = api_set.apis(conf.systems.iter_mut().find(|s| s.name == system)?)?;
+        let position = apis.iter().position(|a| a.name == name)?;
+        apis.remove(position);
+        Some(StatusCode::NO_CONTENT)
+    });
+
+    conf_change_response(
+        outcome,
+        format!("Unknown api '{name}' in {}", api_set.describe(&system)),
+    )
+}
+
+async fn post_rule(
+    State(s): State<MochiRouterState>,
+    Path((system, name)): Path<(String, String)>,
+    Query(api_set): Query<ApiSetQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let rule = match decode::<RuleYaml>(&headers, &body, "rule") {
+        Ok(rule) => rule,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let outcome = s.update_conf(|conf| {
+        api_set.find_api(conf, &system, &name)?.rules.push(rule);
+        Some(StatusCode::CREATED)
+    });
+
+    conf_change_response(
+        outcome,
+        format!("Unknown api '{name}' in {}", api_set.describe(&system)),
+    )
+}
+
+async fn put_rule(
+    State(s): State<MochiRouterState>,
+    Path((system, name, index)): Path<(String, String, usize)>,
+    Query(api_set): Query<ApiSetQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let rule = match decode::<RuleYaml>(&headers, &body, "rule") {
+        Ok(rule) => rule,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let outcome = s.update_conf(|conf| {
+        *api_set
+            .find_api(conf, &system, &name)?
+            .rules
+            .get_mut(index)? = rule;
+        Some(StatusCode::OK)
+    });
+
+    conf_change_response(
+        outcome,
+        format!(
+            "Unknown rule {index} of api '{name}' in {}",
+            api_set.describe(&system)
+        ),
+    )
+}
+
+async fn delete_rule(
+    State(s): State<MochiRouterState>,
+    Path((system, name, index)): Path<(String, String, usize)>,
+    Query(api_set): Query<ApiSetQuery>,
+) -> Response {
+    let outcome = s.update_conf(|conf| {
+        let rules = &mut api_set.find_api(conf, &system, &name)?.rules;
+        if index >= rules.len() {
+            return None;
+        }
+        rules.remove(index);
+        Some(StatusCode::NO_CONTENT)
+    });
+
+    conf_change_response(
+        outcome,
+        format!(
+            "Unknown rule {index} of api '{name}' in {}",
+            api_set.describe(&system)
+        ),
+    )
+}
+
+async fn list_requests(
+    State(s): State<MochiRouterState>,
+    Query(filter): Query<JournalFilter>,
+) -> Response {
+    let path = match filter.path.as_deref().map(Regex::new).transpose() {
+        Ok(path) => path,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, format!("Parsing path regex: {e}")).into_response()
+        }
+    };
+
+    let journal = s.journal.read().unwrap();
+    let entries = journal
+        .entries()
+        .filter(|entry| filter.matches(entry, &path))
+        .collect_vec();
+    Json(entries).into_response()
+}
+
+async fn clear_requests(State(s): State<MochiRouterState>) -> Response {
+    s.journal.write().unwrap().clear();
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn verify_requests(
+    State(s): State<MochiRouterState>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    let verification = match decode::<VerificationYaml>(&headers, &body, "verification")
+        .and_then(|v| extract_verification(&v).map_err(|e| format!("{e:?}")))
+    {
+        Ok(verification) => verification,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+
+    let actual = s
+        .journal
+        .read()
+        .unwrap()
+        .entries()
+        .filter(|entry| verification.matches(entry))
+        .count();
+
+    let verified = match verification.count {
+        Some(count) => actual == count,
+        None => actual > 0,
+    };
+    let status = if verified {
+        StatusCode::OK
+    } else {
+        StatusCode::EXPECTATION_FAILED
+    };
+
+    (
+        status,
+        Json(json!({
+            "verified": verified,
+            "expected": verification.count,
+            "actual": actual,
+        })),
+    )
+        .into_response()
+}
+
+impl ConfCore {
+    pub fn create_admin_router(&self) -> Router<MochiRouterState> {
+        let declared: DeclaredScenarios = Arc::new(
+            self.systems
+                .iter()
+                .map(|system| (system.name.to_owned(), system.scenario_names()))
+                .collect(),
+        );
+
+        let routes = Arc::new(self.route_table());
+        let inspected = declared.clone();
+        let reset_all = declared.clone();
+        let reset_one = declared;
+
+        Router::new()
+            .route(
+                "/routes",
+                get(move || async move { Json(routes.as_ref()).into_response() }),
+            )
+            .route("/requests", get(list_requests).delete(clear_requests))
+            .route("/requests/verify", post(verify_requests))
+            .route("/systems/:system/apis", get(list_apis))
+            .route("/systems/:system/apis/:api", put(put_api).delete(delete_api))
+            .route("/systems/:system/apis/:api/rules", post(post_rule))
+            .route(
+                "/systems/:system/apis/:api/rules/:index",
+                put(put_rule).delete(delete_rule),
+            )
+            .route(
+                "/scenarios/:system",
+                get(
+                    move |State(s): State<MochiRouterState>, Path(system): Path<String>| async move {
+                        let Some(names) = inspected.get(&system) else {
+                            return not_found(&system, None);
+                        };
+                        let state = s.scenarios.read().unwrap();
+                        let states: BTreeMap<&String, String> = names
+                            .iter()
+                            .map(|name| (name, state.state(&system, name)))
+                            .collect();
+                        Json(states).into_response()
+                    },
+                )
+                .delete(
+                    move |State(s): State<MochiRouterState>, Path(system): Path<String>| async move {
+                        if !reset_all.contains_key(&system) {
+                            return not_found(&system, None);
+                        }
+                        s.scenarios.write().unwrap().reset(&system, None);
+                        StatusCode::NO_CONTENT.into_response()
+                    },
+                ),
+            )
+            .route(
+                "/scenarios/:system/:scenario",
+                delete(
+                    move |State(s): State<MochiRouterState>,
+                          Path((system, scenario)): Path<(String, String)>| async move {
+                        if !reset_one
+                            .get(&system)
+                            .is_some_and(|names| names.contains(&scenario))
+                        {
+                            return not_found(&system, Some(&scenario));
+                        }
+                        s.scenarios.write().unwrap().reset(&system, Some(&scenario));
+                        StatusCode::NO_CONTENT.into_response()
+                    },
+                ),
+            )
+    }
+}
@@ -0,0 +1,156 @@
+use crate::core::FaultCore;
+use anyhow::{Context, Result};
+use axum::body::{Body, Bytes};
+use axum::http::response::Builder;
+use axum::http::{Response, StatusCode};
+use futures_util::{stream, StreamExt};
+use rand::random;
+use std::future::{pending, ready};
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context as TaskContext, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::task::yield_now;
+
+// Faults that can only be injected by writing directly on the connection, the response carrying
+// one of them as extension is replaced by the fault when written by the mochi server
+#[derive(Clone, Copy, Debug)]
+pub enum ConnectionFault {
+    Close,
+    MalformedHttp,
+    GarbageBytes,
+}
+
+impl ConnectionFault {
+    fn payload(&self) -> Bytes {
+        match self {
+            ConnectionFault::Close => Bytes::new(),
+            ConnectionFault::MalformedHttp => Bytes::from_static(
+                b"HTTP/1.1 2OO NOT OK\r\nContent-Length: -1\r\nmalformed header line\r\n\r\n",
+            ),
+            ConnectionFault::GarbageBytes => (0..512).map(|_| random::<u8>()).collect(),
+        }
+    }
+}
+
+// Body of unknown size sending the given bytes then ending, with the error if any. The end is delayed
+// so that the server flushes the sent bytes before the body is considered incomplete
+fn partial_body(sent: Bytes, error: Option<io::Error>) -> Body {
+    let end = stream::once(async move {
+        yield_now().await;
+        error.map(Err)
+    });
+    Body::from_stream(stream::once(ready(Ok(sent))).chain(end.filter_map(ready)))
+}
+
+impl FaultCore {
+    pub async fn build_response(
+        &self,
+        response: Builder,
+        content: String,
+    ) -> Result<Response<Body>> {
+        let connection_fault = match self {
+            FaultCore::Hang => return pending().await,
+            FaultCore::ResetMidBody => {
+                // Half of the body is sent before the connection is aborted
+                let content = Bytes::from(content);
+                let sent = content.slice(0..content.len() / 2);
+                return response
+                    .header("Content-Length", content.len())
+                    .body(partial_body(
+                        sent,
+                        Some(io::Error::new(
+                            io::ErrorKind::ConnectionReset,
+                            "connection reset fault injected",
+                        )),
+                    ))
+                    .context("Building reset mid body fault response");
+            }
+            FaultCore::TruncatedContentLength => {
+                // The announced length can never be reached, so the connection is closed after the body
+                return response
+                    .header("Content-Length", content.len() * 2 + 1)
+                    .body(partial_body(Bytes::from(content), None))
+                    .context("Building truncated content length fault response");
+            }
+            FaultCore::CloseConnection => ConnectionFault::Close,
+            FaultCore::MalformedHttp => ConnectionFault::MalformedHttp,
+            FaultCore::GarbageBytes => ConnectionFault::GarbageBytes,
+        };
+
+        response
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .extension(connection_fault)
+            .body(Body::empty())
+            .context("Building connection fault response")
+    }
+}
+
+// Connection writing the payload of the connection fault raised by the current response, if any,
+// instead of the response itself, then aborting
+pub struct FaultyIo<IO> {
+    io: IO,
+    fault: Arc<Mutex<Option<ConnectionFault>>>,
+    payload: Option<Bytes>,
+}
+
+impl<IO> FaultyIo<IO> {
+    pub fn new(io: IO, fault: Arc<Mutex<Option<ConnectionFault>>>) -> FaultyIo<IO> {
+        FaultyIo {
+            io,
+            fault,
+            payload: None,
+        }
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for FaultyIo<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for FaultyIo<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.payload.is_none() {
+            this.payload = this.fault.lock().unwrap().take().map(|f| f.payload());
+        }
+
+        let Some(payload) = &mut this.payload else {
+            return Pin::new(&mut this.io).poll_write(cx, buf);
+        };
+
+        while !payload.is_empty() {
+            let written = ready!(Pin::new(&mut this.io).poll_write(cx, payload))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            let _ = payload.split_to(written);
+        }
+        ready!(Pin::new(&mut this.io).poll_flush(cx))?;
+
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "connection fault injected",
+        )))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
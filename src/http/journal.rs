@@ -0,0 +1,272 @@
+use crate::core::VerificationCore;
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::request::Parts;
+use http_body::{Frame, SizeHint};
+use http_body_util::BodyExt;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Maximum number of requests kept in the journal, the oldest requests are dropped first
+const JOURNAL_CAPACITY: usize = 1000;
+// Bytes of the request bodies kept in the journal, larger bodies are truncated
+pub const JOURNAL_BODY_LIMIT: usize = 64 * 1024;
+
+#[derive(Default)]
+struct Tee {
+    captured: Vec<u8>,
+    // Rest of a body dropped before being read to the end
+    unread: Option<Body>,
+}
+
+impl Tee {
+    fn capture(&mut self, data: &[u8]) {
+        let room = JOURNAL_BODY_LIMIT.saturating_sub(self.captured.len());
+        self.captured
+            .extend_from_slice(&data[..room.min(data.len())]);
+    }
+}
+
+// Request body copying the beginning of its data for the journal while it is read, so forwarded
+// requests are streamed without waiting for the journal
+pub struct JournalBody {
+    inner: Body,
+    // Streams can't be polled once they ended, and don't always tell it with is_end_stream
+    ended: bool,
+    tee: Arc<Mutex<Tee>>,
+}
+
+// Reads the body kept for the journal once the request has been served
+pub struct JournalBodyReader(Arc<Mutex<Tee>>);
+
+impl JournalBody {
+    pub fn tee(inner: Body) -> (Body, JournalBodyReader) {
+        let tee = Arc::new(Mutex::new(Tee::default()));
+        let body = JournalBody {
+            inner,
+            ended: false,
+            tee: tee.clone(),
+        };
+        (Body::new(body), JournalBodyReader(tee))
+    }
+}
+
+impl HttpBody for JournalBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        let frame = ready!(Pin::new(&mut self.inner).poll_frame(cx));
+        self.ended = !matches!(frame, Some(Ok(_)));
+        if let Some(data) = frame
+            .as_ref()
+            .and_then(|frame| frame.as_ref().ok())
+            .and_then(Frame::data_ref)
+        {
+            self.tee.lock().unwrap().capture(data);
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.ended || self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// Handlers answering without reading the body leave it to the journal
+impl Drop for JournalBody {
+    fn drop(&mut self) {
+        if !self.is_end_stream() {
+            self.tee.lock().unwrap().unread = Some(std::mem::take(&mut self.inner));
+        }
+    }
+}
+
+impl JournalBodyReader {
+    // Beginning of the body, read up to the journal limit when the handler didn't read it. The
+    // body of a request still being forwarded is kept as far as it has been read
+    pub async fn read(self) -> Bytes {
+        let unread = self.0.lock().unwrap().unread.take();
+        if let Some(mut body) = unread {
+            while self.0.lock().unwrap().captured.len() < JOURNAL_BODY_LIMIT {
+                match body.frame().await {
+                    Some(Ok(frame)) => {
+                        if let Some(data) = frame.data_ref() {
+                            self.0.lock().unwrap().capture(data);
+                        }
+                    }
+                    _ => break,
+                }
+            }
+        }
+        let captured = std::mem::take(&mut self.0.lock().unwrap().captured);
+        Bytes::from(captured)
+    }
+}
+
+// Rule which produced a response, added to the response extensions by the static handler
+#[derive(Clone, Debug)]
+pub struct MatchedRule {
+    pub system: String,
+    pub api: String,
+    pub rule: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    // Milliseconds since epoch
+    pub received_at: u64,
+    pub duration_ms: u64,
+    pub method: String,
+    pub uri: String,
+    pub path: String,
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+    pub system: Option<String>,
+    pub api: Option<String>,
+    pub rule: Option<String>,
+    pub status: u16,
+}
+
+impl JournalEntry {
+    pub fn new(parts: &Parts, received_at: SystemTime) -> JournalEntry {
+        let mut headers: BTreeMap<String, String> = BTreeMap::new();
+        for (name, value) in parts.headers.iter() {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            headers
+                .entry(name.to_string())
+                .and_modify(|v| *v = format!("{v}, {value}"))
+                .or_insert(value.to_string());
+        }
+
+        JournalEntry {
+            id: 0,
+            received_at: received_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            duration_ms: 0,
+            method: parts.method.to_string(),
+            uri: parts.uri.to_string(),
+            path: parts.uri.path().to_string(),
+            headers,
+            body: String::new(),
+            system: None,
+            api: None,
+            rule: None,
+            status: 0,
+        }
+    }
+
+    pub fn complete(
+        self,
+        body: &Bytes,
+        status: u16,
+        duration: Duration,
+        matched: Option<&MatchedRule>,
+    ) -> Self {
+        JournalEntry {
+            body: String::from_utf8_lossy(body).to_string(),
+            status,
+            duration_ms: duration.as_millis() as u64,
+            system: matched.map(|m| m.system.to_owned()),
+            api: matched.map(|m| m.api.to_owned()),
+            rule: matched.map(|m| m.rule.to_owned()),
+            ..self
+        }
+    }
+}
+
+// Filters of the journal query, the path is a regular expression
+#[derive(Debug, Deserialize)]
+pub struct JournalFilter {
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub system: Option<String>,
+    pub api: Option<String>,
+    pub status: Option<u16>,
+}
+
+impl JournalFilter {
+    pub fn matches(&self, entry: &JournalEntry, path: &Option<Regex>) -> bool {
+        self.method
+            .as_ref()
+            .is_none_or(|method| method.eq_ignore_ascii_case(&entry.method))
+            && path.as_ref().is_none_or(|path| path.is_match(&entry.path))
+            && self
+                .system
+                .as_ref()
+                .is_none_or(|system| entry.system.as_ref() == Some(system))
+            && self
+                .api
+                .as_ref()
+                .is_none_or(|api| entry.api.as_ref() == Some(api))
+            && self.status.is_none_or(|status| status == entry.status)
+    }
+}
+
+#[derive(Debug)]
+pub struct Journal {
+    next_id: u64,
+    entries: VecDeque<JournalEntry>,
+}
+
+impl Journal {
+    pub fn new() -> Journal {
+        Journal {
+            next_id: 1,
+            entries: VecDeque::with_capacity(JOURNAL_CAPACITY),
+        }
+    }
+
+    pub fn record(&mut self, entry: JournalEntry) {
+        if self.entries.len() == JOURNAL_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(JournalEntry {
+            id: self.next_id,
+            ..entry
+        });
+        self.next_id += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.entries.iter()
+    }
+}
+
+impl VerificationCore {
+    pub fn matches(&self, entry: &JournalEntry) -> bool {
+        self.method
+            .as_ref()
+            .is_none_or(|method| method.as_str() == entry.method)
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|path| path.matches(Some(&entry.path)))
+            && self
+                .headers
+                .iter()
+                .all(|(name, matcher)| matcher.matches(entry.headers.get(name).map(String::as_str)))
+            && self
+                .body_matchers
+                .iter()
+                .all(|matcher| matcher.matches(entry.body.as_bytes()))
+    }
+}
@@ -5,10 +5,16 @@ use axum::http::{Request, StatusCode};
 use axum::response::{IntoResponse, Response};
 use log::warn;
 
+mod admin;
+mod fault;
+pub mod journal;
 mod metrics;
 pub mod r#proxy;
+pub mod reload;
 pub mod routes;
-mod r#static;
+pub mod runtime;
+pub mod server;
+pub mod r#static;
 
 pub async fn handler404(
     State(s): State<MochiRouterState>,
@@ -26,5 +32,10 @@ pub async fn handler404(
 }
 
 trait MochiRequestHandler<Req = Body, Res = Body> {
-    async fn handle_request(&self, request: Request<Req>) -> anyhow::Result<Response<Res>>;
+    async fn handle_request(
+        &self,
+        state: &MochiRouterState,
+        system_name: &str,
+        request: Request<Req>,
+    ) -> anyhow::Result<Response<Res>>;
 }
@@ -0,0 +1,106 @@
+use crate::core::{ProxyCore, RuleCore, SystemCore};
+use crate::http::journal::MatchedRule;
+use crate::http::routes::MochiRouterState;
+use crate::http::MochiRequestHandler;
+use anyhow::Context;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use http_body_util::BodyExt;
+
+// Api set whose requests matching no rule are forwarded to its proxy
+#[derive(Clone, Debug)]
+pub struct FallbackProxy {
+    pub system: String,
+    pub api: String,
+    pub proxy: ProxyCore,
+}
+
+impl SystemCore {
+    // Fallback proxy of the api set serving a route of the static router
+    pub fn fallback_proxy(&self, route: &str) -> Option<FallbackProxy> {
+        self.api_sets.iter().find_map(|api_set| {
+            let proxy = api_set.proxy.as_ref().filter(|proxy| proxy.fallback)?;
+            Some(FallbackProxy {
+                system: self.name.to_owned(),
+                api: api_set.name.to_owned(),
+                proxy: proxy.to_owned(),
+            })
+            .filter(|fallback| fallback.serves(route))
+        })
+    }
+}
+
+impl FallbackProxy {
+    // Routes and paths under the api set, the api set name being their first segment
+    pub fn serves(&self, path: &str) -> bool {
+        path.strip_prefix(&format!("/{}", self.api))
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    async fn forward_parts(
+        &self,
+        state: &MochiRouterState,
+        parts: axum::http::request::Parts,
+        body: Body,
+    ) -> Response {
+        // Nested routers strip the /static/{system} prefix, the api name is left
+        let path = parts
+            .uri
+            .path()
+            .strip_prefix(&format!("/{}", self.api))
+            .unwrap_or_default()
+            .trim_start_matches('/')
+            .to_string();
+
+        self.proxy
+            .forward(state, &self.system, &self.api, parts, path, body)
+            .await
+    }
+
+    pub async fn forward(&self, state: &MochiRouterState, request: Request<Body>) -> Response {
+        let (parts, body) = request.into_parts();
+        self.forward_parts(state, parts, body).await
+    }
+
+    // The rules answer first, the request is forwarded when none of them matched it. Its body is
+    // buffered to be given to both when the rules read it, otherwise it is streamed to the proxy
+    pub async fn handle_request(
+        &self,
+        rules: &Vec<RuleCore>,
+        state: &MochiRouterState,
+        request: Request<Body>,
+    ) -> anyhow::Result<Response> {
+        let (parts, body) = request.into_parts();
+        let (rules_body, body) = if rules.iter().any(RuleCore::reads_body) {
+            let body = body
+                .collect()
+                .await
+                .context(format!(
+                    "Collecting body of request with uri [{}] {}",
+                    &parts.method, &parts.uri
+                ))?
+                .to_bytes();
+            (Body::from(body.clone()), Body::from(body))
+        } else {
+            (Body::empty(), body)
+        };
+
+        let response = rules
+            .handle_request(
+                state,
+                &self.system,
+                Request::from_parts(parts.clone(), rules_body),
+            )
+            .await?;
+
+        // Responses of matched rules carry the rule, even when their status is 404
+        if response.status() != StatusCode::NOT_FOUND
+            || response.extensions().get::<MatchedRule>().is_some()
+        {
+            return Ok(response);
+        }
+
+        Ok(self.forward_parts(state, parts, body).await)
+    }
+}
@@ -0,0 +1,102 @@
+use crate::core::HeaderRulesCore;
+use axum::http::header::{
+    CONNECTION, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE,
+};
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use itertools::Itertools;
+use log::warn;
+use std::net::IpAddr;
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+
+// Hop-by-hop headers only concern a single connection, they are not forwarded
+fn is_hop_by_hop(name: &HeaderName) -> bool {
+    [
+        CONNECTION,
+        PROXY_AUTHENTICATE,
+        PROXY_AUTHORIZATION,
+        TE,
+        TRAILER,
+        TRANSFER_ENCODING,
+        UPGRADE,
+    ]
+    .contains(name)
+        || name.as_str() == "keep-alive"
+}
+
+// Headers to forward, without the hop-by-hop ones nor those listed by the Connection header
+pub fn end_to_end_headers(headers: &HeaderMap) -> HeaderMap {
+    let connection_headers = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_lowercase())
+        .collect_vec();
+
+    headers
+        .iter()
+        .filter(|(name, _)| {
+            !is_hop_by_hop(name) && !connection_headers.contains(&name.as_str().to_owned())
+        })
+        .map(|(name, value)| (name.to_owned(), value.to_owned()))
+        .collect()
+}
+
+// The client address is appended to the proxies the request went through, the protocol and host
+// set by a previous proxy are kept
+pub fn add_forwarded_headers(headers: &mut HeaderMap, client: Option<IpAddr>) {
+    if let Some(client) = client {
+        let forwarded_for = match headers.get(&X_FORWARDED_FOR).map(HeaderValue::to_str) {
+            Some(Ok(previous)) => format!("{previous}, {client}"),
+            _ => client.to_string(),
+        };
+        if let Ok(value) = HeaderValue::from_str(&forwarded_for) {
+            headers.insert(X_FORWARDED_FOR, value);
+        }
+    }
+    if !headers.contains_key(&X_FORWARDED_PROTO) {
+        headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static("http"));
+    }
+    if let (false, Some(host)) = (
+        headers.contains_key(&X_FORWARDED_HOST),
+        headers.get(axum::http::header::HOST).cloned(),
+    ) {
+        headers.insert(X_FORWARDED_HOST, host);
+    }
+}
+
+impl HeaderRulesCore {
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for name in self.remove.iter() {
+            headers.remove(name);
+        }
+
+        for (name, pattern, replacement) in self.rewrite.iter() {
+            let values = headers
+                .get_all(name)
+                .iter()
+                .map(|value| match value.to_str() {
+                    Ok(text) => {
+                        let rewritten = pattern.replace_all(text, replacement.as_str());
+                        HeaderValue::from_str(&rewritten).unwrap_or_else(|e| {
+                            warn!("Rewriting header '{name}' to '{rewritten}': {e}");
+                            value.to_owned()
+                        })
+                    }
+                    Err(_) => value.to_owned(),
+                })
+                .collect_vec();
+            headers.remove(name);
+            for value in values {
+                headers.append(name, value);
+            }
+        }
+
+        for (name, value) in self.add.iter() {
+            headers.insert(name, value.to_owned());
+        }
+    }
+}
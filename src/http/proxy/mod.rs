@@ -1,2 +1,5 @@
+pub mod fallback;
+pub mod headers;
+pub mod record;
 pub mod router;
 pub mod state;
@@ -0,0 +1,122 @@
+use crate::yaml::filesystem::fs_data::FsData;
+use crate::yaml::ResponseDataYaml;
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Api file holding the recorded rules, loaded along the other api files of the folder
+const RECORDED_API_FILE: &str = "api-recorded.yml";
+// Recorded responses are kept apart from the hand-written data files
+const RECORDED_DATA_FOLDER: &str = "recorded";
+
+#[derive(Serialize, Deserialize, Default)]
+struct RecordedApi {
+    rules: Vec<RecordedRule>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordedRule {
+    matches: String,
+    response: RecordedResponse,
+}
+
+// Serialized as the !File tag of the api files
+#[derive(Serialize, Deserialize)]
+enum RecordedResponse {
+    File(String),
+}
+
+// FNV-1a hash, stable across runs and builds unlike the hasher of the standard library
+fn fnv_hash(value: &str) -> u32 {
+    value.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+    })
+}
+
+// "GET /users/42?page=1" is stored under the data key "recorded/get-users-42-page-1-<hash>", the
+// hash of the exact endpoint telling apart endpoints with the same words like "GET /users/42/page/1"
+fn data_key(endpoint: &str) -> String {
+    let name = endpoint
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .join("-");
+    format!("{RECORDED_DATA_FOLDER}/{name}-{:08x}", fnv_hash(endpoint))
+}
+
+fn query_len(rule: &RecordedRule) -> usize {
+    rule.matches
+        .split_once('?')
+        .map(|(_, query)| query.split('&').count())
+        .unwrap_or(0)
+}
+
+// Writer of the recordings, shared by the proxies behind its own lock so the file accesses don't
+// hold the proxy state
+#[derive(Default)]
+pub struct Recorder;
+
+impl Recorder {
+    // Writes the response as a data file and the endpoint as a rule of the recorded api file of the
+    // folder, replacing a previous recording of the same endpoint. Taking the recorder mutably keeps
+    // concurrent recordings from overwriting each other
+    pub fn record(
+        &mut self,
+        folder: &Path,
+        endpoint: String,
+        response: &ResponseDataYaml,
+    ) -> Result<()> {
+        // Such segments would be read as parameters of the route
+        let path = endpoint.split(['?', ' ']).nth(1).unwrap_or_default();
+        if path
+            .split('/')
+            .any(|segment| segment.starts_with([':', '*']))
+        {
+            bail!("'{endpoint}' can't be replayed, a segment of its path starts with ':' or '*'");
+        }
+
+        let key = data_key(&endpoint);
+        let data_path = folder.join(FsData::FOLDER).join(format!("{key}.yml"));
+        let api_path = folder.join(RECORDED_API_FILE);
+
+        if let Some(parent) = data_path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Creating data folder '{}'", parent.display()))?;
+        }
+        fs::write(
+            &data_path,
+            serde_yaml::to_string(response)
+                .context(format!("Encoding response of '{endpoint}'"))?,
+        )
+        .context(format!("Writing data file '{}'", data_path.display()))?;
+
+        let mut api: RecordedApi = match fs::read_to_string(&api_path) {
+            Ok(content) => serde_yaml::from_str(&content).context(format!(
+                "Decoding recorded api file '{}'",
+                api_path.display()
+            ))?,
+            Err(_) => RecordedApi::default(),
+        };
+        api.rules.retain(|rule| rule.matches != endpoint);
+        api.rules.push(RecordedRule {
+            matches: endpoint.clone(),
+            response: RecordedResponse::File(key),
+        });
+        // Rules are tried in order, the ones with more query parameters go first so they aren't
+        // shadowed by the same route without them
+        api.rules
+            .sort_by_key(|rule| std::cmp::Reverse(query_len(rule)));
+
+        fs::write(
+            &api_path,
+            serde_yaml::to_string(&api).context("Encoding recorded rules")?,
+        )
+        .context(format!("Writing api file '{}'", api_path.display()))?;
+
+        info!("Recorded '{endpoint}' in '{}'", folder.display());
+        Ok(())
+    }
+}
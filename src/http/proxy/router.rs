@@ -1,115 +1,238 @@
-use crate::core::SystemCore;
+use crate::core::{ProxyCore, SystemCore};
 use crate::http::handler404;
+use crate::http::proxy::headers::{add_forwarded_headers, end_to_end_headers};
+use crate::yaml::ResponseDataYaml;
 use crate::MochiRouterState;
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use axum::body::Body;
-use axum::extract::{Path, Request, State};
-use axum::http::{HeaderValue, Method, StatusCode, Uri};
+use axum::body::{Bytes, HttpBody};
+use axum::extract::{ConnectInfo, Path, Request, State};
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE, DATE, HOST};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{any, get};
 use axum::Router;
+use http_body_util::BodyExt;
 use itertools::Itertools;
-use log::debug;
-use std::convert::Infallible;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+type ProxiedResponse = (StatusCode, HeaderMap, Body);
+
+impl ProxyCore {
+    // Forwards a request of an api set to its upstream, recording the response when asked to
+    pub async fn forward(
+        &self,
+        state: &MochiRouterState,
+        system_name: &str,
+        api_name: &str,
+        parts: Parts,
+        path: String,
+        body: Body,
+    ) -> Response {
+        state.metrics.mochi_proxy_request_counter(
+            system_name,
+            Some(&api_name.to_string()),
+            &self.url.to_string(),
+            &path,
+        );
+
+        let _ = state
+            .proxy
+            .write()
+            .map(|mut w| w.append_path(path.split('/').collect()));
+
+        // Recorded with the path as received, the decoded one may not be a valid route
+        let raw_path = parts
+            .uri
+            .path()
+            .strip_prefix(&format!("/{api_name}"))
+            .unwrap_or_default()
+            .trim_start_matches('/');
+        let endpoint = match parts.uri.query() {
+            Some(query) => format!("{} /{raw_path}?{query}", parts.method),
+            None => format!("{} /{raw_path}", parts.method),
+        };
+
+        let proxied = match self.handle_proxy_request(parts, path, body).await {
+            Ok((status, headers, body)) => match &self.record {
+                // Recorded responses are buffered to be written, others are streamed back
+                Some(folder) => match body.collect().await {
+                    Ok(body) => {
+                        let body = body.to_bytes();
+                        if let Err(e) = SystemCore::record_proxy_response(
+                            state, folder, endpoint, status, &headers, &body,
+                        )
+                        .await
+                        {
+                            warn!("Could not record proxied response: {e:#}");
+                        }
+                        Ok((status, headers, Body::from(body)))
+                    }
+                    Err(e) => Err(anyhow!(e).context("Getting body of the proxied response")),
+                },
+                None => Ok((status, headers, body)),
+            },
+            Err(e) => Err(e),
+        };
+
+        match proxied {
+            Ok((status, headers, body)) => {
+                let mut response = (status, body).into_response();
+                *response.headers_mut() = headers;
+                response
+            }
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
 
-impl SystemCore {
     async fn handle_proxy_request(
-        request_method: Method,
-        request_uri: Uri,
-        target_url: Uri,
+        &self,
+        request: Parts,
         target_path: String,
-        request_body: String,
-    ) -> anyhow::Result<Response> {
-        let query_params = match request_uri.query() {
+        request_body: Body,
+    ) -> anyhow::Result<ProxiedResponse> {
+        let target_url = &self.url;
+        let query_params = match request.uri.query() {
             Some(str) => format!("?{str}"),
             None => "".to_string(),
         };
 
         let reconstructed_uri = format!("{target_url}{target_path}{query_params}");
 
-        debug!("Request received {request_uri} and redirecting to {reconstructed_uri}");
+        debug!(
+            "Request received {} and redirecting to {reconstructed_uri}",
+            request.uri
+        );
 
         let new_url = reqwest::Url::parse(reconstructed_uri.as_str())
             .context(format!("Reconstructing target uri {reconstructed_uri}"))?;
 
-        let response = reqwest::Client::new()
-            .request(request_method, new_url)
-            .body(request_body)
-            .send()
-            .await
-            .context(format!(
-                "Sending request/receiving response from {target_url}"
-            ))?;
-
-        Response::builder()
-            .status(response.status())
-            .header(
-                "Content-Type",
-                response
-                    .headers()
-                    .get("Content-Type")
-                    .unwrap_or(&HeaderValue::from_static("text/plain")),
-            )
-            .body(Body::from(response.bytes().await.context(format!(
-                "Getting bytes from http client response (from {})",
-                &reconstructed_uri
-            ))?))
-            .context("Building response to http server request")
+        // The http client sets the host of the forwarded request
+        let mut request_headers = end_to_end_headers(&request.headers);
+        let client = request
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip());
+        add_forwarded_headers(&mut request_headers, client);
+        request_headers.remove(HOST);
+        self.request_headers.apply(&mut request_headers);
+
+        // Streamed bodies can't be sent again, requests without body can follow any redirect
+        let mut forwarded = self
+            .client
+            .request(request.method, new_url)
+            .headers(request_headers);
+        if request_body.size_hint().exact() != Some(0) {
+            forwarded = forwarded.body(reqwest::Body::wrap_stream(request_body.into_data_stream()));
+        }
+
+        let response = forwarded.send().await.context(format!(
+            "Sending request/receiving response from {target_url}"
+        ))?;
+
+        let status = response.status();
+        let mut headers = end_to_end_headers(response.headers());
+        if !headers.contains_key(CONTENT_TYPE) {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        }
+        self.response_headers.apply(&mut headers);
+
+        let body = Body::from_stream(response.bytes_stream());
+
+        Ok((status, headers, body))
     }
+}
+
+impl SystemCore {
+    // Recorded responses are replayed by the static router once the configuration is reloaded,
+    // the date of the recording isn't kept
+    async fn record_proxy_response(
+        state: &MochiRouterState,
+        folder: &std::path::Path,
+        endpoint: String,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &Bytes,
+    ) -> anyhow::Result<()> {
+        let recorded_headers: HashMap<String, String> = headers
+            .iter()
+            .filter(|(name, _)| ![CONTENT_TYPE, CONTENT_LENGTH, DATE].contains(name))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+
+        let response = ResponseDataYaml {
+            status: status.as_u16(),
+            description: None,
+            format: headers
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+            headers: match recorded_headers.is_empty() {
+                true => None,
+                false => Some(recorded_headers),
+            },
+            data: match body.is_empty() {
+                true => None,
+                false => Some(
+                    String::from_utf8(body.to_vec())
+                        .context(format!("Response body of '{endpoint}' isn't text"))?,
+                ),
+            },
+        };
+
+        // The files are written apart from the async runtime
+        let recorder = state.recorder.clone();
+        let folder = folder.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            recorder
+                .lock()
+                .map_err(|e| anyhow!(e.to_string()))?
+                .record(&folder, endpoint, &response)
+        })
+        .await
+        .context("Recording proxied response")?
+    }
+
     pub fn create_proxy_router(&self) -> Router<MochiRouterState> {
         let system = self;
         let mut proxy_router: Router<MochiRouterState> = Router::new();
+
+        // Paths proxied so far, shared by the proxied api sets of the system
+        if system.api_sets.iter().any(|api| api.proxy.is_some()) {
+            proxy_router = proxy_router.route(
+                "/config",
+                get(|State(s): State<MochiRouterState>| async move {
+                    let state = s.proxy.read().unwrap();
+                    state
+                        .routes
+                        .iter()
+                        .map(|r| r.display(0))
+                        .format("\n")
+                        .to_string()
+                        .into_response()
+                }),
+            );
+        }
+
         for api in system.api_sets.iter() {
             if let Some(p) = &api.proxy {
-                let url = p.0.clone();
+                let proxy = p.clone();
                 let system_name = system.name.clone();
                 let api_name = api.name.clone();
 
-                proxy_router = proxy_router.route(
-                    "/config",
-                    get(|State(s): State<MochiRouterState>| async move {
-                        let state = s.proxy.read().unwrap();
-                        state
-                            .routes
-                            .iter()
-                            .map(|r| r.display(0))
-                            .format("\n")
-                            .to_string()
-                            .into_response()
-                    }),
-                );
-
                 proxy_router = proxy_router.route(
                     &format!("/{}/*path", &api_name),
                     any(
-                        move |s: State<MochiRouterState>,
-                              method: Method,
-                              uri: Uri,
+                        move |State(s): State<MochiRouterState>,
                               Path(path): Path<String>,
-                              body: String| async move {
-                            s.metrics.mochi_proxy_request_counter(
-                                &system_name,
-                                Some(&api_name),
-                                &url.to_string(),
-                                &path,
-                            );
-
-                            let _ = s
-                                .proxy
-                                .write()
-                                .map(|mut w| w.append_path(path.split('/').collect()));
-
-                            dbg!(&s.proxy);
-
-                            match SystemCore::handle_proxy_request(method, uri, url, path, body)
+                              parts: Parts,
+                              body: Body| async move {
+                            proxy
+                                .forward(&s, &system_name, &api_name, parts, path, body)
                                 .await
-                            {
-                                Ok(content) => Ok::<_, Infallible>(content),
-                                Err(e) => Ok::<_, Infallible>(
-                                    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
-                                        .into_response(),
-                                ),
-                            }
                         },
                     ),
                 )
@@ -0,0 +1,86 @@
+use crate::http::routes::MochiRouterState;
+use crate::yaml::from_files::ConfigurationFolder;
+use anyhow::{Context, Result};
+use log::{debug, error, info};
+use notify::{recommended_watcher, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+// Editors and kubelet (configmap updates swap a symlink) touch several files for a single change,
+// events are gathered until the configuration folder is quiet for this long
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+// Watches the configuration folder as long as it is alive
+pub struct ConfWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+fn is_change(event: &notify::Result<Event>) -> bool {
+    match event {
+        Ok(event) => event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove(),
+        Err(e) => {
+            error!("Watching configuration folder: {e}");
+            false
+        }
+    }
+}
+
+impl MochiRouterState {
+    // Reloads the whole configuration from the filesystem, the previous one is kept when the new
+    // one can't be loaded or is invalid
+    fn reload_conf(&self, conf_path: &str) {
+        let outcome = ConfigurationFolder::new(conf_path.to_owned())
+            .load_strictly_from_filesystem()
+            .and_then(|conf| {
+                self.update_conf(|current| {
+                    *current = conf;
+                    Some(())
+                })
+            });
+
+        match outcome {
+            Ok(_) => info!("Reloaded configuration from '{conf_path}'"),
+            Err(e) => error!(
+                "Could not reload configuration from '{conf_path}', the previous one is kept: {e:?}"
+            ),
+        }
+    }
+
+    fn reload_on_changes(&self, conf_path: String, events: Receiver<notify::Result<Event>>) {
+        // The channel is closed when the watcher is dropped
+        while let Ok(event) = events.recv() {
+            if !is_change(&event) {
+                continue;
+            }
+            debug!("Change detected in configuration folder '{conf_path}': {event:?}");
+
+            loop {
+                match events.recv_timeout(RELOAD_DEBOUNCE) {
+                    Ok(_) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            self.reload_conf(&conf_path);
+        }
+    }
+
+    pub fn watch_conf(&self, conf_path: String) -> Result<ConfWatcher> {
+        let (sender, events) = channel();
+        let mut watcher =
+            recommended_watcher(sender).context("Could not create configuration folder watcher")?;
+        watcher
+            .watch(Path::new(&conf_path), RecursiveMode::Recursive)
+            .context(format!(
+                "Could not watch configuration folder '{conf_path}'"
+            ))?;
+
+        let state = self.clone();
+        thread::spawn(move || state.reload_on_changes(conf_path, events));
+
+        Ok(ConfWatcher { _watcher: watcher })
+    }
+}
@@ -1,27 +1,48 @@
-use crate::core::ConfCore;
+use crate::core::{ConfCore, RandomCore};
+use crate::yaml::to_domain::extract_random;
 use axum::body::Body;
 use axum::http::Request;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 use crate::http::handler404;
+use crate::http::journal::Journal;
 use crate::http::metrics::MochiMetrics;
+use crate::http::proxy::record::Recorder;
 use crate::http::proxy::state::ProxyState;
+use crate::http::r#static::matcher::request_host;
+use crate::http::r#static::state::{ScenarioState, SequenceState};
+use crate::http::runtime::Runtime;
 use axum::extract::State;
 use axum::Router;
+use std::collections::HashMap;
+use tower::ServiceExt;
 
 #[derive(Clone)]
 pub struct MochiRouterState {
     pub metrics: MochiMetrics,
     pub proxy: Arc<RwLock<ProxyState>>,
+    pub recorder: Arc<Mutex<Recorder>>,
+    pub scenarios: Arc<RwLock<ScenarioState>>,
+    pub sequences: Arc<RwLock<SequenceState>>,
+    pub random: RandomCore,
+    pub runtime: Arc<Runtime>,
+    pub journal: Arc<RwLock<Journal>>,
 }
 
 impl MochiRouterState {
-    pub fn new() -> MochiRouterState {
+    // The seed makes the random choices of responses reproducible
+    pub fn new(seed: Option<u64>) -> MochiRouterState {
         let mochi_metrics = MochiMetrics::new();
         let proxy_state = ProxyState::new();
         MochiRouterState {
             metrics: mochi_metrics,
             proxy: Arc::new(RwLock::new(proxy_state)),
+            recorder: Arc::new(Mutex::new(Recorder)),
+            scenarios: Arc::new(RwLock::new(ScenarioState::new())),
+            sequences: Arc::new(RwLock::new(SequenceState::new())),
+            random: extract_random(&seed),
+            runtime: Arc::new(Runtime::new()),
+            journal: Arc::new(RwLock::new(Journal::new())),
         }
     }
 }
@@ -30,13 +51,23 @@ impl ConfCore {
     pub fn build_router(
         &self,
         initial_router: Router<MochiRouterState>,
-    ) -> Router<MochiRouterState> {
+    ) -> anyhow::Result<Router<MochiRouterState>> {
         let mut global_router: Router<MochiRouterState> = initial_router;
+        let mut virtual_hosts: HashMap<String, Router<MochiRouterState>> = HashMap::new();
 
         for system in self.systems.iter() {
-            let static_router = system.create_static_router();
+            // Nesting panics on wildcards, and the name must stay one segment of the path
+            if system.name.contains(['/', ':', '*']) {
+                anyhow::bail!("Invalid system name '{}'", system.name);
+            }
+            let static_router = system.create_static_router()?;
             let proxy_router = system.create_proxy_router();
 
+            // Systems declaring hosts are also served at the root of their virtual hosts
+            for host in system.hosts.iter() {
+                virtual_hosts.insert(host.to_owned(), static_router.clone());
+            }
+
             // Proxy setup
 
             global_router = global_router
@@ -44,8 +75,23 @@ impl ConfCore {
                 .nest(&format!("/proxy/{}", &system.name), proxy_router)
         }
 
-        global_router.fallback(move |m: State<MochiRouterState>, r: Request<Body>| {
-            handler404(m, r, "Mochi System".to_string())
-        })
+        // Administration of the running mocks
+        global_router = global_router.nest("/_admin", self.create_admin_router());
+
+        Ok(global_router.fallback(
+            move |State(s): State<MochiRouterState>, r: Request<Body>| async move {
+                let virtual_host = request_host(r.headers(), r.uri())
+                    .and_then(|host| virtual_hosts.get(&host))
+                    .cloned();
+
+                match virtual_host {
+                    Some(router) => match router.with_state(s).oneshot(r).await {
+                        Ok(response) => response,
+                        Err(infallible) => match infallible {},
+                    },
+                    None => handler404(State(s), r, "Mochi System".to_string()).await,
+                }
+            },
+        ))
     }
 }
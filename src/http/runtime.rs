@@ -0,0 +1,132 @@
+use crate::core::{ApiCore, ConfCore, RuleResponseCore};
+use crate::http::journal::{JournalBody, JournalEntry, MatchedRule};
+use crate::http::routes::MochiRouterState;
+use crate::yaml::ConfFolder;
+use anyhow::Result;
+use axum::body::Body;
+use axum::extract::Request;
+use axum::response::Response;
+use axum::Router;
+use axum_otel_metrics::{HttpMetricsLayer, HttpMetricsLayerBuilder};
+use std::collections::HashSet;
+use std::sync::{Mutex, RwLock};
+use std::time::{Instant, SystemTime};
+use tower::ServiceExt;
+
+// Configuration served by mochi, the router is rebuilt from it each time it changes at runtime
+pub struct Runtime {
+    conf: Mutex<ConfFolder>,
+    router: RwLock<Router<()>>,
+    metrics_layer: HttpMetricsLayer,
+}
+
+impl Runtime {
+    pub fn new() -> Runtime {
+        Runtime {
+            conf: Mutex::new(ConfFolder { systems: vec![] }),
+            router: RwLock::new(Router::new()),
+            metrics_layer: HttpMetricsLayerBuilder::new()
+                .with_service_name("mochi".to_ascii_uppercase())
+                .build(),
+        }
+    }
+}
+
+// Sequences of responses of a configuration, by system
+fn sequence_ids(conf: &ConfCore) -> HashSet<(String, String)> {
+    conf.systems
+        .iter()
+        .flat_map(|system| {
+            system
+                .root_api_set
+                .apis
+                .iter()
+                .chain(
+                    system
+                        .api_sets
+                        .iter()
+                        .flat_map(|api_set| api_set.apis.iter()),
+                )
+                .flat_map(|ApiCore(rules)| rules.iter())
+                .filter_map(|rule| match &rule.response {
+                    RuleResponseCore::Sequence { id, .. } => {
+                        Some((system.name.to_owned(), id.to_owned()))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+impl MochiRouterState {
+    pub fn conf(&self) -> ConfFolder {
+        self.runtime.conf.lock().unwrap().clone()
+    }
+
+    pub fn router(&self) -> Router<()> {
+        self.runtime.router.read().unwrap().clone()
+    }
+
+    // Serves a request with the router of the current configuration, and records it in the journal
+    // unless it's an administration request
+    pub async fn handle(&self, request: Request<Body>) -> Response {
+        if request.uri().path().starts_with("/_admin") {
+            return self.dispatch(request).await;
+        }
+
+        let received_at = SystemTime::now();
+        let start = Instant::now();
+
+        let (parts, body) = request.into_parts();
+        let entry = JournalEntry::new(&parts, received_at);
+        let (body, journal_body) = JournalBody::tee(body);
+
+        let response = self.dispatch(Request::from_parts(parts, body)).await;
+
+        let body = journal_body.read().await;
+        self.journal.write().unwrap().record(entry.complete(
+            &body,
+            response.status().as_u16(),
+            start.elapsed(),
+            response.extensions().get::<MatchedRule>(),
+        ));
+
+        response
+    }
+
+    async fn dispatch(&self, request: Request<Body>) -> Response {
+        match self.router().oneshot(request).await {
+            Ok(response) => response,
+            Err(infallible) => match infallible {},
+        }
+    }
+
+    // Applies a change to the served configuration and swaps the router, nothing is changed when
+    // the change is not applicable (none) or when the new configuration is invalid (error)
+    pub fn update_conf<T>(
+        &self,
+        change: impl FnOnce(&mut ConfFolder) -> Option<T>,
+    ) -> Result<Option<T>> {
+        let mut conf = self.runtime.conf.lock().unwrap();
+        let mut new_conf = conf.clone();
+
+        let Some(outcome) = change(&mut new_conf) else {
+            return Ok(None);
+        };
+
+        let metrics_layer = self.runtime.metrics_layer.clone();
+        let conf_core = new_conf.extract()?;
+        let sequences = sequence_ids(&conf_core);
+        let router = conf_core
+            .build_router(metrics_layer.routes::<MochiRouterState>())?
+            .layer(metrics_layer)
+            .with_state(self.clone());
+
+        *self.runtime.router.write().unwrap() = router;
+        *conf = new_conf;
+        self.sequences.write().unwrap().retain(&sequences);
+
+        Ok(Some(outcome))
+    }
+}
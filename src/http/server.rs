@@ -0,0 +1,61 @@
+use crate::http::fault::{ConnectionFault, FaultyIo};
+use anyhow::Result;
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request};
+use axum::Router;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use log::{debug, error};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::time::sleep;
+use tower::ServiceExt;
+
+// Serves the application, connection level faults raised by responses are written on the connection
+pub async fn serve(tcp_listener: TcpListener, app: Router<()>) -> Result<()> {
+    loop {
+        let (tcp_stream, remote_addr) = match tcp_listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Accepting tcp connection: {e}");
+                sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let app = app.clone();
+
+        tokio::spawn(async move {
+            let fault: Arc<Mutex<Option<ConnectionFault>>> = Arc::new(Mutex::new(None));
+            let io = TokioIo::new(FaultyIo::new(tcp_stream, fault.clone()));
+
+            let service = service_fn(move |request: Request<Incoming>| {
+                let app = app.clone();
+                let fault = fault.clone();
+                async move {
+                    let mut request = request.map(Body::new);
+                    request.extensions_mut().insert(ConnectInfo(remote_addr));
+
+                    let response = app.oneshot(request).await?;
+
+                    if let Some(connection_fault) = response.extensions().get::<ConnectionFault>() {
+                        *fault.lock().unwrap() = Some(*connection_fault);
+                    }
+
+                    Ok::<_, Infallible>(response)
+                }
+            });
+
+            if let Err(e) = Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(io, service)
+                .await
+            {
+                debug!("Serving connection from {remote_addr}: {e}");
+            }
+        });
+    }
+}
@@ -0,0 +1,214 @@
+use crate::core::{BodyMatcherCore, MatcherCore, RuleCore, ScenarioCore};
+use crate::http::r#static::state::STARTED_STATE;
+use axum::body::{Body, Bytes};
+use axum::extract::Query;
+use axum::http::header::{COOKIE, HOST};
+use axum::http::uri::Authority;
+use axum::http::{HeaderMap, HeaderValue, Request, Uri};
+use itertools::Itertools;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use sxd_document::parser;
+use sxd_xpath::{Context, Factory};
+
+impl MatcherCore {
+    pub fn matches(&self, value: Option<&str>) -> bool {
+        match self {
+            MatcherCore::Exact(expected) => value == Some(expected.as_str()),
+            MatcherCore::Regex(regex) => value.is_some_and(|v| regex.is_match(v)),
+            MatcherCore::Prefix(prefix) => value.is_some_and(|v| v.starts_with(prefix.as_str())),
+            MatcherCore::Present => value.is_some(),
+            MatcherCore::Absent => value.is_none(),
+            MatcherCore::Not(matcher) => !matcher.matches(value),
+        }
+    }
+
+    // Header values that are not valid utf8 never match
+    pub fn matches_header(&self, value: Option<&HeaderValue>) -> bool {
+        match value.map(|v| std::str::from_utf8(v.as_bytes())) {
+            None => self.matches(None),
+            Some(Ok(str)) => self.matches(Some(str)),
+            Some(Err(_)) => false,
+        }
+    }
+}
+
+// Host of the request without its port, taken from the Host header or from the uri authority
+pub fn request_host(headers: &HeaderMap, uri: &Uri) -> Option<String> {
+    match headers.get(HOST) {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|host| Authority::from_str(host).ok())
+            .map(|authority| authority.host().to_ascii_lowercase()),
+        None => uri.host().map(str::to_ascii_lowercase),
+    }
+}
+
+// Cookies sent in every Cookie header of the request, cookies that are not valid utf8 are skipped
+fn request_cookies(headers: &HeaderMap) -> HashMap<&str, &str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| std::str::from_utf8(value.as_bytes()).ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .collect()
+}
+
+// Every field and array item of the expected json must be found in the actual json
+fn json_contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual_value| json_contains(actual_value, value))
+        }),
+        (Value::Array(actual), Value::Array(expected)) => expected.iter().all(|value| {
+            actual
+                .iter()
+                .any(|actual_value| json_contains(actual_value, value))
+        }),
+        _ => actual == expected,
+    }
+}
+
+// An xpath predicate is satisfied with the same rules as the xpath boolean() function
+fn xpath_matches(body: &str, query: &str, namespaces: &HashMap<String, String>) -> bool {
+    let Ok(package) = parser::parse(body) else {
+        return false;
+    };
+    let document = package.as_document();
+    let Ok(Some(xpath)) = Factory::new().build(query) else {
+        return false;
+    };
+    let mut context = Context::new();
+    for (prefix, uri) in namespaces {
+        context.set_namespace(prefix, uri);
+    }
+
+    match xpath.evaluate(&context, document.root()) {
+        Ok(sxd_xpath::Value::Nodeset(nodeset)) => nodeset.size() > 0,
+        Ok(sxd_xpath::Value::Boolean(value)) => value,
+        Ok(sxd_xpath::Value::Number(value)) => value != 0.0 && !value.is_nan(),
+        Ok(sxd_xpath::Value::String(value)) => !value.is_empty(),
+        Err(_) => false,
+    }
+}
+
+impl BodyMatcherCore {
+    pub fn matches(&self, body: &[u8]) -> bool {
+        match self {
+            BodyMatcherCore::Json(expected) => {
+                serde_json::from_slice::<Value>(body).is_ok_and(|actual| actual == *expected)
+            }
+            BodyMatcherCore::JsonPartial(expected) => serde_json::from_slice::<Value>(body)
+                .is_ok_and(|actual| json_contains(&actual, expected)),
+            BodyMatcherCore::JsonPath(path) => serde_json::from_slice::<Value>(body)
+                .is_ok_and(|actual| !path.query(&actual).is_empty()),
+            BodyMatcherCore::XPath { query, namespaces } => {
+                std::str::from_utf8(body).is_ok_and(|str| xpath_matches(str, query, namespaces))
+            }
+            BodyMatcherCore::Regex(regex) => {
+                std::str::from_utf8(body).is_ok_and(|str| regex.is_match(str))
+            }
+        }
+    }
+}
+
+// Request values the rules are matched against, computed once per request
+pub struct RequestFacts<'a> {
+    pub headers: &'a HeaderMap,
+    pub host: Option<String>,
+    pub cookies: HashMap<&'a str, &'a str>,
+    pub query: HashMap<String, String>,
+    pub body: Bytes,
+    pub scenarios: HashMap<String, String>,
+}
+
+impl<'a> RequestFacts<'a> {
+    pub fn new(
+        request: &'a Request<Body>,
+        body: Bytes,
+        scenarios: HashMap<String, String>,
+    ) -> RequestFacts<'a> {
+        RequestFacts {
+            headers: request.headers(),
+            host: request_host(request.headers(), request.uri()),
+            cookies: request_cookies(request.headers()),
+            // Undecodable query strings are considered as empty
+            query: Query::try_from_uri(request.uri())
+                .map(|Query(q)| q)
+                .unwrap_or_default(),
+            body,
+            scenarios,
+        }
+    }
+}
+
+impl RuleCore {
+    // Reasons why the rule rejects the request, empty when the rule matches
+    pub fn mismatches(&self, request: &RequestFacts) -> Vec<String> {
+        let mut reasons = vec![];
+
+        // All api headers must match the corresponding headers in the received request
+        for (key, matcher) in self.headers.iter().sorted_by_key(|(k, _)| *k) {
+            if !matcher.matches_header(request.headers.get(key)) {
+                reasons.push(format!("expected header '{key}' to be {matcher}"));
+            }
+        }
+
+        // All api cookies must match the corresponding cookies in the received request
+        for (name, matcher) in self.cookies.iter().sorted_by_key(|(k, _)| *k) {
+            if !matcher.matches(request.cookies.get(name.as_str()).copied()) {
+                reasons.push(format!("expected cookie '{name}' to be {matcher}"));
+            }
+        }
+
+        if let Some(matcher) = &self.host {
+            if !matcher.matches(request.host.as_deref()) {
+                reasons.push(format!("expected host to be {matcher}"));
+            }
+        }
+
+        // All rule query parameters must match the corresponding parameters in the received request
+        for (key, matcher) in self.query.iter().sorted_by_key(|(k, _)| *k) {
+            if !matcher.matches(request.query.get(key).map(String::as_str)) {
+                reasons.push(format!("expected query parameter '{key}' to be {matcher}"));
+            }
+        }
+
+        // All rule body matchers must match the received request body
+        for matcher in self.body_matchers.iter() {
+            if !matcher.matches(&request.body) {
+                reasons.push(format!("expected body to be {matcher}"));
+            }
+        }
+
+        // Scenario rules only match in the required state of their scenario
+        if let Some(scenario) = &self.scenario {
+            let state = request
+                .scenarios
+                .get(&scenario.name)
+                .map(String::as_str)
+                .unwrap_or(STARTED_STATE);
+            reasons.extend(scenario.mismatch(state));
+        }
+
+        reasons
+    }
+}
+
+impl ScenarioCore {
+    pub fn mismatch(&self, state: &str) -> Option<String> {
+        match &self.required_state {
+            Some(required_state) if required_state != state => Some(format!(
+                "expected scenario '{}' to be in state '{required_state}', found '{state}'",
+                self.name
+            )),
+            _ => None,
+        }
+    }
+}
@@ -1,2 +1,4 @@
+pub mod matcher;
 mod request_handler;
-mod router;
+pub mod router;
+pub mod state;
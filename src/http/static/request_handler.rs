@@ -1,27 +1,111 @@
-use crate::core::{LatencyCore, RuleBodyCore, RuleCore};
+use crate::core::{
+    ChaosCore, ChaosEffectCore, LatencyCore, LatencyDistributionCore, RandomCore, ResponseCore,
+    RuleBodyCore, RuleCore, RuleResponseCore, SequenceModeCore, ThrottleCore,
+};
+use crate::http::journal::MatchedRule;
+use crate::http::r#static::matcher::RequestFacts;
+use crate::http::routes::MochiRouterState;
 use crate::http::MochiRequestHandler;
 use crate::template::render::build_templated_response_body;
-use anyhow::{bail, Context};
-use axum::body::Body;
-use axum::http::Request;
+use crate::yaml::openapi::request_schema_errors;
+use anyhow::Context;
+use axum::body::{Body, Bytes};
+use axum::extract::OriginalUri;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderValue, Request, StatusCode};
 use axum::response::Response;
+use futures_util::{stream, StreamExt};
+use http_body_util::BodyExt;
+use log::warn;
+use rand::distributions::{Distribution, Standard, Uniform};
+use rand::thread_rng;
+use std::convert::Infallible;
 use std::time::Duration;
 use tokio::time::sleep;
 
+impl RandomCore {
+    pub fn sample<T, D: Distribution<T>>(&self, distribution: &D) -> T {
+        match self {
+            RandomCore::Thread => distribution.sample(&mut thread_rng()),
+            RandomCore::Seeded(rng) => distribution.sample(&mut *rng.lock().unwrap()),
+        }
+    }
+}
+
+impl LatencyDistributionCore {
+    fn sample_millis(&self, random: &RandomCore) -> f64 {
+        match self {
+            LatencyDistributionCore::Uniform(d) => random.sample(d),
+            LatencyDistributionCore::Normal(d) => random.sample(d),
+            LatencyDistributionCore::LogNormal(d) => random.sample(d),
+            LatencyDistributionCore::Pareto(d) => random.sample(d),
+            LatencyDistributionCore::Percentiles(points) => {
+                let quantile: f64 = random.sample(&Standard);
+                points
+                    .windows(2)
+                    .find(|w| quantile < w[1].0)
+                    .map(|w| {
+                        let ((q0, v0), (q1, v1)) = (w[0], w[1]);
+                        v0 + (v1 - v0) * (quantile - q0) / (q1 - q0)
+                    })
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
 impl LatencyCore {
     async fn compute_latency(&self) {
         match self {
             LatencyCore::Constant(v) => sleep(Duration::from_millis((*v).into())).await,
+            LatencyCore::Random {
+                distribution,
+                random,
+            } => {
+                // Negative samples of unbounded distributions are considered as no latency
+                let millis = distribution.sample_millis(random).max(0.0);
+                sleep(Duration::try_from_secs_f64(millis / 1000.0).unwrap_or_default()).await
+            }
+        }
+    }
+}
+
+impl ChaosCore {
+    fn roll(&self) -> Option<&ChaosEffectCore> {
+        let roll: f64 = self.random.sample(&Uniform::new(0.0, 100.0));
+        self.faults
+            .iter()
+            .find(|(upper_bound, _)| roll < *upper_bound)
+            .map(|(_, effect)| effect)
+    }
+}
+
+impl ChaosEffectCore {
+    async fn build_response(&self) -> anyhow::Result<Response<Body>> {
+        let response = Response::builder().header("Content-Type", "text/plain");
+
+        match self {
+            ChaosEffectCore::Status(status) => response.status(status).body(Body::empty()),
+            ChaosEffectCore::Timeout(millis) => {
+                sleep(Duration::from_millis((*millis).into())).await;
+                response
+                    .status(StatusCode::GATEWAY_TIMEOUT)
+                    .body(Body::empty())
+            }
+            ChaosEffectCore::Fault(fault) => {
+                return fault.build_response(response, String::new()).await
+            }
         }
+        .context("Could not generate chaos response")
     }
 }
 
 impl RuleBodyCore {
-    async fn generate_response_body(&self, request: Request<Body>) -> anyhow::Result<Body> {
+    async fn generate_response_body(&self, request: Request<Body>) -> anyhow::Result<String> {
         let uri = request.uri().clone();
         let method = request.method().clone();
 
-        Ok(Body::from(match self {
+        Ok(match self {
             RuleBodyCore::Plain(content) => content.clone(),
             RuleBodyCore::Templated {
                 has_variables,
@@ -31,43 +115,322 @@ impl RuleBodyCore {
                 .context(format!(
                     "Generating response body for request received on [{method}] {uri}"
                 ))?,
-        }))
+        })
+    }
+}
+
+impl ThrottleCore {
+    // Time needed to send a chunk of the body, the first chunk is sent without chunk delay
+    fn chunk_duration(&self, pos: usize, chunk_len: usize) -> Duration {
+        let transfer = self
+            .throughput
+            .map(|t| Duration::from_secs_f64(chunk_len as f64 / t as f64))
+            .unwrap_or_default();
+        let delay = if pos == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_millis(self.chunk_delay.into())
+        };
+        transfer + delay
+    }
+
+    fn stream_body(&self, content: Bytes) -> Body {
+        let throttle = self.clone();
+        let chunks: Vec<Bytes> = (0..content.len())
+            .step_by(self.chunk_size)
+            .map(|start| content.slice(start..content.len().min(start + self.chunk_size)))
+            .collect();
+
+        Body::from_stream(
+            stream::iter(chunks.into_iter().enumerate()).then(move |(pos, chunk)| {
+                let duration = throttle.chunk_duration(pos, chunk.len());
+                async move {
+                    sleep(duration).await;
+                    Ok::<_, Infallible>(chunk)
+                }
+            }),
+        )
+    }
+}
+
+impl ResponseCore {
+    fn has_templated_headers(&self) -> bool {
+        self.headers
+            .iter()
+            .any(|(_, value)| matches!(value, RuleBodyCore::Templated { .. }))
+    }
+
+    fn has_body_template(&self) -> bool {
+        matches!(
+            &self.body,
+            Some(RuleBodyCore::Templated { has_variables, .. })
+                if has_variables.has_body_json || has_variables.has_body_text
+        )
+    }
+
+    async fn build_response(
+        &self,
+        request: Request<Body>,
+        body: &Bytes,
+        throttle: Option<&ThrottleCore>,
+    ) -> anyhow::Result<Response<Body>> {
+        let mut response = Response::builder()
+            .header("Content-Type", self.format.to_owned())
+            .status(self.status);
+
+        for (name, value) in self.headers.iter() {
+            let value = value
+                .generate_response_body(copy_request(&request, body))
+                .await
+                .context(format!("Generating response header '{name}'"))?;
+            let value = HeaderValue::from_str(&value).context(format!(
+                "Parsing value '{value}' of response header '{name}'"
+            ))?;
+            // Declared headers replace the default ones, like the content type
+            if let Some(headers) = response.headers_mut() {
+                headers.insert(name, value);
+            }
+        }
+
+        let content = match &self.body {
+            Some(b) => b.generate_response_body(request).await?,
+            None => String::new(),
+        };
+
+        if let Some(fault) = &self.fault {
+            return fault.build_response(response, content).await;
+        }
+
+        match throttle {
+            // Throttled bodies are streamed, the length is still announced to the client
+            Some(throttle) => response
+                .header("Content-Length", content.len())
+                .body(throttle.stream_body(Bytes::from(content))),
+            None => response.body(Body::from(content)),
+        }
+        .context("Could not generate response body")
+    }
+}
+
+impl RuleResponseCore {
+    pub fn responses(&self) -> &[ResponseCore] {
+        match self {
+            RuleResponseCore::Single(response) => std::slice::from_ref(response),
+            RuleResponseCore::Sequence { responses, .. }
+            | RuleResponseCore::OneOf { responses, .. } => responses,
+        }
+    }
+
+    // Response returned for the current request, none once a sequence played once is exhausted
+    fn next_response(&self, state: &MochiRouterState, system_name: &str) -> Option<&ResponseCore> {
+        match self {
+            RuleResponseCore::Single(response) => Some(response),
+            RuleResponseCore::Sequence {
+                id,
+                mode,
+                responses,
+            } => {
+                let position = state
+                    .sequences
+                    .write()
+                    .unwrap()
+                    .next_position(system_name, id);
+                match mode {
+                    SequenceModeCore::Once => responses.get(position),
+                    SequenceModeCore::Cycle => responses.get(position % responses.len()),
+                    SequenceModeCore::StickLast => responses.get(position.min(responses.len() - 1)),
+                }
+            }
+            RuleResponseCore::OneOf { responses, weights } => {
+                responses.get(state.random.sample(weights))
+            }
+        }
+    }
+}
+
+impl RuleCore {
+    // The request body is needed before answering to match or validate it, or to render several
+    // templates from it
+    fn needs_body(&self) -> bool {
+        !self.body_matchers.is_empty()
+            || self.request_schema.is_some()
+            || self
+                .response
+                .responses()
+                .iter()
+                .any(ResponseCore::has_templated_headers)
+    }
+
+    // The request body is read by the rule, to answer or to render its response
+    pub fn reads_body(&self) -> bool {
+        self.needs_body()
+            || self
+                .response
+                .responses()
+                .iter()
+                .any(ResponseCore::has_body_template)
     }
+
+    fn matched_rule(&self, system_name: &str) -> MatchedRule {
+        MatchedRule {
+            system: system_name.to_owned(),
+            api: self.api.to_owned(),
+            rule: self.endpoint.to_string(),
+        }
+    }
+}
+
+// Copy of a request whose body has been buffered, to render several templates from it
+fn copy_request(request: &Request<Body>, body: &Bytes) -> Request<Body> {
+    let mut copy = Request::new(Body::from(body.clone()));
+    *copy.method_mut() = request.method().clone();
+    *copy.uri_mut() = request.uri().clone();
+    *copy.version_mut() = request.version();
+    *copy.headers_mut() = request.headers().clone();
+    *copy.extensions_mut() = request.extensions().clone();
+    copy
 }
 
 impl MochiRequestHandler for Vec<RuleCore> {
-    async fn handle_request(&self, request: Request<Body>) -> anyhow::Result<Response<Body>> {
-        for rule in self.iter() {
-            // All api headers must match the corresponding headers in the received request
-            let matching_request = rule.headers.iter().all(|(key, value)| {
-                request
-                    .headers()
-                    .get(key)
-                    .map(move |req_header_value| req_header_value.to_str().unwrap() == *value)
-                    .unwrap_or(false)
-            });
-
-            if matching_request {
+    async fn handle_request(
+        &self,
+        state: &MochiRouterState,
+        system_name: &str,
+        request: Request<Body>,
+    ) -> anyhow::Result<Response<Body>> {
+        // The request body is only buffered when a rule needs it before answering
+        let (request, body) = if self.iter().any(RuleCore::needs_body) {
+            let (parts, body) = request.into_parts();
+            let bytes = body
+                .collect()
+                .await
+                .context(format!(
+                    "Collecting body of request with uri [{}] {}",
+                    &parts.method, &parts.uri
+                ))?
+                .to_bytes();
+            (Request::from_parts(parts, Body::from(bytes.clone())), bytes)
+        } else {
+            (request, Bytes::new())
+        };
+
+        let scenarios = state.scenarios.read().unwrap().states(system_name);
+        let facts = RequestFacts::new(&request, body, scenarios);
+        let mut rejections = vec![];
+
+        for (pos, rule) in self.iter().enumerate() {
+            let mut mismatches = rule.mismatches(&facts);
+
+            // Requests not following the schema of their operation are rejected before responding
+            if let (true, Some(schema)) = (mismatches.is_empty(), &rule.request_schema) {
+                let content_type = facts
+                    .headers
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok());
+                let errors = request_schema_errors(schema, content_type, &facts.body);
+                if !errors.is_empty() {
+                    let mut response = Response::builder()
+                        .header("Content-Type", "text/plain")
+                        .status(StatusCode::BAD_REQUEST)
+                        .body(Body::from(format!(
+                            "Request body doesn't follow the schema of the openapi document:\n- {}",
+                            errors.join("\n- ")
+                        )))
+                        .context("Could not generate request validation response")?;
+                    response
+                        .extensions_mut()
+                        .insert(rule.matched_rule(system_name));
+                    return Ok(response);
+                }
+            }
+
+            // Sequences of responses are only consumed by matching requests. The scenario state is
+            // checked again and moved under one lock, so that concurrent requests can't both match
+            // the same state, and only when the rule serves its own response rather than chaos
+            let served = if mismatches.is_empty() {
+                let mut scenarios = rule
+                    .scenario
+                    .as_ref()
+                    .map(|scenario| (scenario, state.scenarios.write().unwrap()));
+                let scenario_mismatch = scenarios.as_ref().and_then(|(scenario, states)| {
+                    scenario.mismatch(&states.state(system_name, &scenario.name))
+                });
+
+                match scenario_mismatch {
+                    Some(mismatch) => {
+                        mismatches.push(mismatch);
+                        None
+                    }
+                    None => rule
+                        .response
+                        .next_response(state, system_name)
+                        .map(|response| {
+                            let effect = rule.chaos.as_ref().and_then(|chaos| chaos.roll());
+                            if let (None, Some((scenario, states))) = (effect, scenarios.as_mut()) {
+                                if let Some(new_state) = &scenario.new_state {
+                                    states.set_state(system_name, &scenario.name, new_state);
+                                }
+                            }
+                            (response, effect)
+                        }),
+                }
+            } else {
+                None
+            };
+
+            if let Some((response, effect)) = served {
                 if let Some(value) = &rule.latency {
                     value.compute_latency().await
                 };
 
-                let body = match &rule.body {
-                    Some(b) => b.generate_response_body(request).await?,
-                    None => Body::empty(),
+                let body = facts.body.clone();
+                let mut response = match effect {
+                    // Chaos faults replace the response of the matched rule
+                    Some(effect) => effect.build_response().await?,
+                    None => {
+                        response
+                            .build_response(request, &body, rule.throttle.as_ref())
+                            .await?
+                    }
                 };
 
-                return Response::builder()
-                    .header("Content-Type", rule.format.to_owned())
-                    .status(rule.status)
-                    .body(body)
-                    .context("Could not generate response body");
+                response
+                    .extensions_mut()
+                    .insert(rule.matched_rule(system_name));
+
+                return Ok(response);
+            }
+
+            if mismatches.is_empty() {
+                mismatches.push("expected sequence of responses not to be exhausted".to_string());
             }
+
+            rejections.push(format!(
+                "- rule {} '{}' (priority {}):\n  - {}",
+                pos + 1,
+                rule.endpoint,
+                rule.priority,
+                mismatches.join("\n  - ")
+            ));
         }
 
-        let uri = request.uri();
+        // Nested routers strip their prefix from the request uri
+        let uri = request
+            .extensions()
+            .get::<OriginalUri>()
+            .map(|OriginalUri(uri)| uri)
+            .unwrap_or(request.uri());
         let method = request.method();
 
-        bail!("No rule matched for request [{method}] {uri}");
+        warn!("No rule matched for request [{method}] {uri}");
+
+        Response::builder()
+            .header("Content-Type", "text/plain")
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::from(format!(
+                "No rule matched for request [{method}] {uri}\n{}",
+                rejections.join("\n")
+            )))
+            .context("Could not generate rule mismatch response")
     }
 }
@@ -1,15 +1,159 @@
-use crate::core::{ApiCore, HttpRoute, RuleCore, SystemCore};
+use crate::core::{ApiCore, ConfCore, HttpRoute, ResponseCore, RuleCore, SystemCore};
 use crate::http::{handler404, MochiRequestHandler};
 use crate::MochiRouterState;
+use anyhow::{anyhow, Context, Result};
 use axum::body::Body;
 use axum::extract::State;
 use axum::http::{Request, StatusCode};
 use axum::response::IntoResponse;
-use axum::routing::{on, MethodFilter};
+use axum::routing::{MethodFilter, MethodRouter};
 use axum::Router;
+use itertools::Itertools;
+use serde::Serialize;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 
 type SystemRulesMap = HashMap<HttpRoute, Vec<RuleCore>>;
+
+// Rule of the route table, in the order rules are tried for a route
+#[derive(Serialize, Debug)]
+pub struct RouteEntry {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<String>,
+    // Other conditions of the rule on the request: query, cookies, host, body and scenario
+    pub guards: Vec<String>,
+    pub latency: Option<String>,
+    pub status: String,
+    pub content_type: String,
+    pub source: String,
+}
+
+impl RouteEntry {
+    fn new(system: &str, route: &str, rule: &RuleCore) -> RouteEntry {
+        let responses = rule.response.responses();
+        let described = |describe: fn(&ResponseCore) -> String| {
+            responses.iter().map(describe).unique().join(", ")
+        };
+
+        RouteEntry {
+            method: rule.endpoint.method.to_string(),
+            url: format!("/static/{system}{route}"),
+            headers: rule
+                .headers
+                .iter()
+                .map(|(name, matcher)| format!("{name} {matcher}"))
+                .sorted()
+                .collect(),
+            guards: rule
+                .query
+                .iter()
+                .map(|(name, matcher)| format!("query {name} {matcher}"))
+                .sorted()
+                .chain(
+                    rule.cookies
+                        .iter()
+                        .map(|(name, matcher)| format!("cookie {name} {matcher}"))
+                        .sorted(),
+                )
+                .chain(rule.host.iter().map(|matcher| format!("host {matcher}")))
+                .chain(
+                    rule.body_matchers
+                        .iter()
+                        .map(|matcher| format!("body {matcher}")),
+                )
+                .chain(rule.scenario.iter().map(|scenario| scenario.to_string()))
+                .collect(),
+            latency: rule.latency.as_ref().map(|latency| latency.to_string()),
+            status: described(|response| response.status.as_u16().to_string()),
+            content_type: described(|response| response.format.to_owned()),
+            source: rule
+                .source
+                .to_owned()
+                .unwrap_or_else(|| format!("admin api '{}'", rule.api)),
+        }
+    }
+}
+
+// Route table as aligned text columns, for the command line
+pub fn format_route_table(routes: &[RouteEntry]) -> String {
+    let header = [
+        "METHOD",
+        "URL",
+        "HEADERS",
+        "GUARDS",
+        "LATENCY",
+        "STATUS",
+        "CONTENT TYPE",
+        "SOURCE",
+    ]
+    .map(String::from);
+    let rows = routes.iter().map(|route| {
+        [
+            route.method.to_owned(),
+            route.url.to_owned(),
+            match route.headers.is_empty() {
+                true => "-".to_string(),
+                false => route.headers.join(", "),
+            },
+            match route.guards.is_empty() {
+                true => "-".to_string(),
+                false => route.guards.join(", "),
+            },
+            route.latency.to_owned().unwrap_or("-".to_string()),
+            route.status.to_owned(),
+            route.content_type.to_owned(),
+            route.source.to_owned(),
+        ]
+    });
+    let columns = header.len();
+    let lines = std::iter::once(header).chain(rows).collect_vec();
+
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            lines
+                .iter()
+                .map(|line| line[column].chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    lines
+        .iter()
+        .map(|line| {
+            line.iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .join("\n")
+}
+
+impl ConfCore {
+    pub fn route_table(&self) -> Vec<RouteEntry> {
+        self.systems
+            .iter()
+            .flat_map(|system| {
+                system
+                    .generate_rules_map()
+                    .into_iter()
+                    .sorted_by(|(a, _), (b, _)| {
+                        (&a.route, a.method.as_str()).cmp(&(&b.route, b.method.as_str()))
+                    })
+                    .flat_map(|(HttpRoute { route, .. }, rules)| {
+                        rules
+                            .iter()
+                            .map(|rule| RouteEntry::new(&system.name, &route, rule))
+                            .collect_vec()
+                    })
+                    .collect_vec()
+            })
+            .collect()
+    }
+}
 impl SystemCore {
     pub fn generate_rules_map(&self) -> SystemRulesMap {
         let mut rules_map: SystemRulesMap = HashMap::new();
@@ -46,34 +190,82 @@ impl SystemCore {
             }
         }
 
+        // Rules with the highest priority are tried first, api files and rules order is kept otherwise
+        for rules in rules_map.values_mut() {
+            rules.sort_by_key(|rule| Reverse(rule.priority));
+        }
+
         rules_map
     }
-    pub fn create_static_router(&self) -> Router<MochiRouterState> {
+    pub fn create_static_router(&self) -> Result<Router<MochiRouterState>> {
         let mut router = Router::new();
+        // Axum panics on invalid or conflicting routes, they are checked first with the same
+        // matcher to be reported as configuration errors
+        let mut checked = matchit::Router::new();
         let system_name = self.name.clone();
-        // static sub router built from the ./config folder
-        for (HttpRoute { route, method }, rules) in self.generate_rules_map().into_iter() {
-            router = router.route(
-                &route,
-                on(MethodFilter::try_from(method.clone()).unwrap(), {
-                    move |request: Request<Body>| async move {
-                        match rules.handle_request(request).await {
+        // static sub router built from the ./config folder, the methods of a route share its router
+        let routes = self
+            .generate_rules_map()
+            .into_iter()
+            .into_group_map_by(|(HttpRoute { route, .. }, _)| route.to_owned());
+        for (route, method_rules) in routes {
+            checked.insert(route.as_str(), ()).context(format!(
+                "Registering route '{route}' of system '{}'",
+                self.name
+            ))?;
+            let fallback = self.fallback_proxy(&route);
+            let mut method_router = MethodRouter::new();
+            for (HttpRoute { method, .. }, rules) in method_rules {
+                let system_name = self.name.clone();
+                let fallback = fallback.clone();
+                method_router = method_router.on(
+                    MethodFilter::try_from(method.clone())
+                        .map_err(|_| anyhow!("Unsupported method '{method}'"))?,
+                    move |State(s): State<MochiRouterState>, request: Request<Body>| async move {
+                        let response = match &fallback {
+                            Some(fallback) => fallback.handle_request(&rules, &s, request).await,
+                            None => rules.handle_request(&s, &system_name, request).await,
+                        };
+                        match response {
                             Ok(res) => res.into_response(),
                             Err(e) => {
                                 (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
                             }
                         }
-                    }
-                }),
-            )
+                    },
+                );
+            }
+
+            // Methods without rules are forwarded by hybrid api sets
+            if let Some(fallback) = fallback {
+                method_router = method_router.fallback(
+                    move |State(s): State<MochiRouterState>, request: Request<Body>| async move {
+                        fallback.forward(&s, request).await
+                    },
+                );
+            }
+            router = router.route(&route, method_router)
         }
 
+        // Routes without rules are forwarded by hybrid api sets. Going through the fallback rather than
+        // a catch-all route keeps them from conflicting with the parameters of the declared routes
+        let fallbacks = self
+            .api_sets
+            .iter()
+            .filter_map(|api_set| self.fallback_proxy(&format!("/{}", api_set.name)))
+            .collect_vec();
         router = router.fallback(
             move |m: State<MochiRouterState>, r: Request<Body>| async move {
-                handler404(m, r, system_name).await
+                match fallbacks
+                    .iter()
+                    .find(|fallback| fallback.serves(r.uri().path()))
+                {
+                    Some(fallback) => fallback.forward(&m.0, r).await,
+                    None => handler404(m, r, system_name).await,
+                }
             },
         );
 
-        router
+        Ok(router)
     }
 }
@@ -0,0 +1,80 @@
+use std::collections::{HashMap, HashSet};
+
+// State of every scenario before its first transition or after a reset
+pub const STARTED_STATE: &str = "Started";
+
+// Current state of the scenarios of each system, only scenarios which left their initial state are stored
+#[derive(Debug)]
+pub struct ScenarioState {
+    systems: HashMap<String, HashMap<String, String>>,
+}
+
+impl ScenarioState {
+    pub fn new() -> ScenarioState {
+        ScenarioState {
+            systems: HashMap::new(),
+        }
+    }
+
+    pub fn states(&self, system: &str) -> HashMap<String, String> {
+        self.systems.get(system).cloned().unwrap_or_default()
+    }
+
+    pub fn state(&self, system: &str, scenario: &str) -> String {
+        self.systems
+            .get(system)
+            .and_then(|scenarios| scenarios.get(scenario))
+            .map(String::to_owned)
+            .unwrap_or(STARTED_STATE.to_string())
+    }
+
+    pub fn set_state(&mut self, system: &str, scenario: &str, state: &str) {
+        self.systems
+            .entry(system.to_owned())
+            .or_default()
+            .insert(scenario.to_owned(), state.to_owned());
+    }
+
+    // Resets a scenario of a system, or all of them without scenario name
+    pub fn reset(&mut self, system: &str, scenario: Option<&str>) {
+        match (self.systems.get_mut(system), scenario) {
+            (Some(scenarios), Some(scenario)) => {
+                scenarios.remove(scenario);
+            }
+            (Some(_), None) => {
+                self.systems.remove(system);
+            }
+            (None, _) => {}
+        }
+    }
+}
+
+// Number of responses already returned by each sequence of responses of each system
+#[derive(Debug)]
+pub struct SequenceState {
+    positions: HashMap<(String, String), usize>,
+}
+
+impl SequenceState {
+    pub fn new() -> SequenceState {
+        SequenceState {
+            positions: HashMap::new(),
+        }
+    }
+
+    // Position of the response to return for the current request
+    pub fn next_position(&mut self, system: &str, id: &str) -> usize {
+        let position = self
+            .positions
+            .entry((system.to_owned(), id.to_owned()))
+            .or_default();
+        *position += 1;
+        *position - 1
+    }
+
+    // Forgets the positions of the sequences which are no longer configured
+    pub fn retain(&mut self, sequences: &HashSet<(String, String)>) {
+        self.positions
+            .retain(|sequence, _| sequences.contains(sequence));
+    }
+}
@@ -1,27 +1,74 @@
+use crate::http::r#static::router::format_route_table;
 use crate::http::routes::MochiRouterState;
 use crate::yaml::from_files::ConfigurationFolder;
-use anyhow::Result;
+use anyhow::{Error, Result};
+use axum::body::Body;
+use axum::extract::{Request, State};
 use axum::Router;
-use axum_otel_metrics::HttpMetricsLayerBuilder;
 
 mod core;
 mod http;
 mod template;
 mod yaml;
 
+pub use crate::http::reload::ConfWatcher;
+pub use crate::http::server::serve;
+
 pub fn setup_app(conf_path: String) -> Result<Router<()>> {
-    let metrics_layer = HttpMetricsLayerBuilder::new()
-        .with_service_name("mochi".to_ascii_uppercase())
-        .build();
+    setup_seeded_app(conf_path, None)
+}
+
+// Seeded applications draw the same random responses for the same sequence of requests
+pub fn setup_seeded_app(conf_path: String, seed: Option<u64>) -> Result<Router<()>> {
+    Ok(setup_state(conf_path, seed)?.1)
+}
+
+// The configuration is reloaded each time the configuration folder changes, as long as the
+// returned watcher is alive
+pub fn setup_watched_app(
+    conf_path: String,
+    seed: Option<u64>,
+) -> Result<(Router<()>, ConfWatcher)> {
+    let (state, app) = setup_state(conf_path.to_owned(), seed)?;
+    let watcher = state.watch_conf(conf_path)?;
+    Ok((app, watcher))
+}
 
-    let core_representation = ConfigurationFolder::new(conf_path)
+// All the errors of a configuration, an empty list meaning the configuration is valid
+pub fn validate_conf(conf_path: String) -> Vec<Error> {
+    let folder = ConfigurationFolder::new(conf_path);
+    let errors = match folder.load_from_filesystem() {
+        Ok(conf) => conf.validate(),
+        Err(e) => vec![e],
+    };
+    folder.take_skipped().into_iter().chain(errors).collect()
+}
+
+// Rules served by a configuration, as a table with a rule per line
+pub fn route_table(conf_path: String) -> Result<String> {
+    let conf = ConfigurationFolder::new(conf_path)
         .load_from_filesystem()?
         .extract()?;
+    Ok(format_route_table(&conf.route_table()))
+}
+
+fn setup_state(conf_path: String, seed: Option<u64>) -> Result<(MochiRouterState, Router<()>)> {
+    let conf = ConfigurationFolder::new(conf_path).load_from_filesystem()?;
+
+    let state = MochiRouterState::new(seed);
+    state.update_conf(|current| {
+        *current = conf;
+        Some(())
+    })?;
 
-    let initial_router = metrics_layer.routes::<MochiRouterState>();
+    // Requests are served by the router of the current configuration, which can change at runtime
+    let app = Router::new()
+        .fallback(
+            |State(s): State<MochiRouterState>, request: Request<Body>| async move {
+                s.handle(request).await
+            },
+        )
+        .with_state(state.clone());
 
-    Ok(core_representation
-        .build_router(initial_router)
-        .layer(metrics_layer)
-        .with_state(MochiRouterState::new()))
+    Ok((state, app))
 }
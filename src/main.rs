@@ -5,15 +5,31 @@ use log::info;
 mod logger;
 mod setup;
 use crate::logger::setup_logger;
-use crate::setup::{start_server, Config};
+use crate::setup::{print_routes, start_server, validate, Command, Config};
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let config = Config::parse();
 
+    // Errors are printed by the validation, the skipped files are not logged again
+    match config.command {
+        Some(Command::Validate { config_path }) => {
+            return Ok(match validate(config_path) {
+                true => ExitCode::SUCCESS,
+                false => ExitCode::FAILURE,
+            });
+        }
+        Some(Command::Routes { config_path }) => {
+            print_routes(config_path)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        None => {}
+    }
+
     setup_logger().context("Failed to setup logger")?;
     info!("Starting Mochi!");
 
     start_server(config.server).await?;
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
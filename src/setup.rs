@@ -1,15 +1,34 @@
 use anyhow::{Context, Error, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::info;
-use mochi::setup_app;
+use mochi::{route_table, serve, setup_seeded_app, setup_watched_app, validate_conf};
 use std::net::{IpAddr, SocketAddr};
 use tokio::net::TcpListener;
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 pub struct Config {
     #[clap(flatten)]
     pub server: ServerConfig,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check the configuration and print all its errors, without starting the server
+    Validate {
+        /// Path to configuration file
+        #[clap(long, short, env = "CONFIG_PATH", default_value = "./config")]
+        config_path: String,
+    },
+    /// Print the rules served by the configuration, in the order they are tried for each route
+    Routes {
+        /// Path to configuration file
+        #[clap(long, short, env = "CONFIG_PATH", default_value = "./config")]
+        config_path: String,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -25,10 +44,27 @@ pub struct ServerConfig {
     /// IPv4 address to bind to
     #[clap(long, short, env = "IP_ADDR", default_value = "0.0.0.0")]
     ip_addr: String,
+
+    /// Seed of the random choices of responses, to reproduce a run
+    #[clap(long, env = "SEED")]
+    seed: Option<u64>,
+
+    /// Reload the configuration each time the configuration folder changes
+    #[clap(long, short, env = "WATCH_CONFIG")]
+    watch: bool,
 }
 
 pub async fn start_server(config: ServerConfig) -> Result<(), Error> {
-    let app = setup_app(config.config_path).context("Failed to setup application")?;
+    // The watcher stops watching the configuration folder once dropped, when the server stops
+    let (app, _watcher) = if config.watch {
+        let (app, watcher) = setup_watched_app(config.config_path, config.seed)
+            .context("Failed to setup application")?;
+        (app, Some(watcher))
+    } else {
+        let app = setup_seeded_app(config.config_path, config.seed)
+            .context("Failed to setup application")?;
+        (app, None)
+    };
     let ip: IpAddr = config
         .ip_addr
         .parse()
@@ -41,7 +77,31 @@ pub async fn start_server(config: ServerConfig) -> Result<(), Error> {
 
     info!("Listening on: {}", addr);
 
-    axum::serve(tcp_listener, app.into_make_service())
+    serve(tcp_listener, app)
         .await
         .context("Failed to start HTTP server")
 }
+
+// Prints the errors of the configuration, returns whether it is valid
+pub fn validate(config_path: String) -> bool {
+    let errors = validate_conf(config_path.to_owned());
+    if errors.is_empty() {
+        println!("Configuration '{config_path}' is valid");
+        return true;
+    }
+
+    for e in errors.iter() {
+        eprintln!("error: {e:#}");
+    }
+    eprintln!(
+        "Configuration '{config_path}' has {} error(s)",
+        errors.len()
+    );
+    false
+}
+
+pub fn print_routes(config_path: String) -> Result<(), Error> {
+    let table = route_table(config_path).context("Failed to load configuration")?;
+    println!("{table}");
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use crate::yaml::filesystem::fs_system::FsSystem;
 use anyhow::{Context, Result};
+use itertools::Itertools;
 use log::debug;
 use std::fs;
 use std::path::PathBuf;
@@ -28,6 +29,7 @@ impl FsConfig {
             // Just keep directories
             .filter_map(|e| e.ok())
             .filter(|entity| entity.metadata().map(|m| m.is_dir()).unwrap_or(false))
+            .sorted_by_key(|entity| entity.file_name())
             .map(|system_path| FsSystem::new(system_path.path()))
             .collect())
     }
@@ -20,6 +20,7 @@ impl FsData {
             self.path.display()
         );
         WalkDir::new(self.path.clone())
+            .sort_by_file_name()
             .into_iter()
             // Keeps files only
             .filter_map(|i| i.ok())
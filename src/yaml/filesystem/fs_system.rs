@@ -2,6 +2,7 @@ use crate::yaml::filesystem::fs_api::FsApi;
 use crate::yaml::filesystem::fs_data::FsData;
 use crate::yaml::filesystem::fs_system_file::FsSystemFile;
 use anyhow::{bail, Context, Result};
+use itertools::Itertools;
 use log::{debug, warn};
 use std::fs;
 use std::fs::DirEntry;
@@ -15,6 +16,8 @@ impl FsSystem {
     pub const API_FILE_PREFIX: &'static str = "api";
     pub const SHAPE_FILE_PREFIX: &'static str = "shape";
     pub const PROXY_FILE_PREFIX: &'static str = "proxy";
+    pub const HOST_FILE_PREFIX: &'static str = "host";
+    pub const OPENAPI_FILE_PREFIX: &'static str = "openapi";
 
     pub fn new(path: PathBuf) -> FsSystem {
         FsSystem { path }
@@ -32,7 +35,7 @@ impl FsSystem {
         }
     }
 
-    // Entries loaded per system FOLDER (./config/system/*)
+    // Entries loaded per system FOLDER (./config/system/*), sorted by name so loading is deterministic
     fn get_entries(&self) -> Result<Vec<DirEntry>> {
         debug!(
             "Iterating over entries of system folder '{}'",
@@ -45,6 +48,7 @@ impl FsSystem {
                 self.path.display()
             ))?
             .filter_map(|e| e.ok())
+            .sorted_by_key(|e| e.file_name())
             .collect::<Vec<_>>())
     }
 
@@ -99,10 +103,18 @@ impl FsSystem {
         self.iter_over_prefixed_files(FsSystem::PROXY_FILE_PREFIX)
     }
 
+    pub fn iter_host_files(&self) -> Result<Vec<FsSystemFile>> {
+        self.iter_over_prefixed_files(FsSystem::HOST_FILE_PREFIX)
+    }
+
     pub fn iter_shape_files(&self) -> Result<Vec<FsSystemFile>> {
         self.iter_over_prefixed_files(FsSystem::SHAPE_FILE_PREFIX)
     }
 
+    pub fn iter_openapi_files(&self) -> Result<Vec<FsSystemFile>> {
+        self.iter_over_prefixed_files(FsSystem::OPENAPI_FILE_PREFIX)
+    }
+
     pub fn iter_api_folders(&self) -> Result<Vec<FsApi>> {
         debug!(
             "Iterating over api folders of system folder '{}'",
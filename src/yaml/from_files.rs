@@ -3,24 +3,37 @@ use crate::yaml::filesystem::fs_config::FsConfig;
 use crate::yaml::filesystem::fs_data::FsData;
 use crate::yaml::filesystem::fs_data_file::FsDataFile;
 use crate::yaml::filesystem::fs_system::FsSystem;
+use crate::yaml::openapi::import_openapi;
 use crate::yaml::{
-    ApiFolder, ApiShapeYaml, ApiYaml, ConfFolder, ProxyYaml, ResponseDataYaml, SystemFolder,
+    ApiFolder, ApiShapeYaml, ApiYaml, ConfFolder, HostYaml, ProxyYaml, ResponseDataYaml,
+    SystemFolder,
 };
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Error, Result};
+use itertools::Itertools;
 use log::{debug, error};
 use serde_yaml::from_str;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub struct ConfigurationFolder {
     folder: String,
+    skipped: RefCell<Vec<Error>>,
 }
 
 impl ConfigurationFolder {
     pub fn new(path: String) -> ConfigurationFolder {
-        ConfigurationFolder { folder: path }
+        ConfigurationFolder {
+            folder: path,
+            skipped: RefCell::new(vec![]),
+        }
+    }
+    // Files that can't be decoded are skipped, the rest of the configuration is still loaded
+    pub(self) fn skip(&self, e: Error) {
+        error!("{:?}", e);
+        self.skipped.borrow_mut().push(e);
     }
     pub(self) fn load_fs_data_file(fs_data_file: FsDataFile) -> Result<(String, ResponseDataYaml)> {
         let path = fs_data_file.path.display().to_string();
@@ -37,30 +50,59 @@ impl ConfigurationFolder {
             .into_string()
             .unwrap();
 
-        let file_content = fs::read_to_string(&fs_data_file.path)
-            .context(format!("Could not read data file '{path}'"))?;
-
-        let yaml_response_data_file_content: ResponseDataYaml = from_str(&file_content)
+        let yaml_response_data_file_content: ResponseDataYaml = from_str(&fs_data_file.content)
             .context(format!("Could not decode response data yaml file '{path}'"))?;
 
         Ok((filename_key, yaml_response_data_file_content))
     }
-    pub(self) fn load_fs_data(fs_data: FsData) -> Result<HashMap<String, ResponseDataYaml>> {
+    pub(self) fn named_api(api: ApiYaml, path: &Path) -> ApiYaml {
+        ApiYaml {
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: Some(path.display().to_string()),
+            ..api
+        }
+    }
+    // Shapes referencing an openapi document are loaded with its content
+    pub(self) fn load_shape(content: &str, path: &Path) -> Result<ApiShapeYaml> {
+        let shape: ApiShapeYaml = from_str(content)?;
+        let openapi_document = match &shape.openapi {
+            Some(openapi) => {
+                let document_path = path.with_file_name(openapi);
+                Some(fs::read_to_string(&document_path).context(format!(
+                    "Could not read openapi document '{}'",
+                    document_path.display()
+                ))?)
+            }
+            None => None,
+        };
+        Ok(ApiShapeYaml {
+            openapi_document,
+            ..shape
+        })
+    }
+    pub(self) fn load_fs_data(&self, fs_data: FsData) -> Result<HashMap<String, ResponseDataYaml>> {
         debug!("Loading data folder '{}'", fs_data.path.display());
 
-        fs_data
+        Ok(fs_data
             .iter_files()?
             .into_iter()
-            .map(ConfigurationFolder::load_fs_data_file)
-            .collect()
+            .filter_map(|file| {
+                ConfigurationFolder::load_fs_data_file(file)
+                    .map_err(|e| self.skip(e))
+                    .ok()
+            })
+            .collect())
     }
 
-    pub(self) fn load_fs_api_folder(fs_api: FsApi) -> Result<ApiFolder> {
+    pub(self) fn load_fs_api_folder(&self, fs_api: FsApi) -> Result<ApiFolder> {
         let api_path = fs_api.path.display();
         debug!("Loading api folder '{api_path}'");
 
         let data = match fs_api.get_data_folder()? {
-            Some(fs_data) => ConfigurationFolder::load_fs_data(fs_data)?,
+            Some(fs_data) => self.load_fs_data(fs_data)?,
             None => HashMap::new(),
         };
 
@@ -73,8 +115,9 @@ impl ConfigurationFolder {
                         "Failed to decode api '{}' in api folder '{api_path}'",
                         file.path.display()
                     ))
-                    .map_err(|e| error!("{:?}", e))
+                    .map_err(|e| self.skip(e))
                     .ok()
+                    .map(|api| ConfigurationFolder::named_api(api, &file.path))
             })
             .collect();
 
@@ -83,12 +126,12 @@ impl ConfigurationFolder {
                 .iter_shape_files()?
                 .into_iter()
                 .find_map(|file| -> Option<ApiShapeYaml> {
-                    from_str(&file.content)
+                    ConfigurationFolder::load_shape(&file.content, &file.path)
                         .context(format!(
                             "Failed to decode api shape '{}' in api folder '{api_path}'",
                             file.path.display()
                         ))
-                        .map_err(|e| error!("{:?}", e))
+                        .map_err(|e| self.skip(e))
                         .ok()
                 });
 
@@ -98,11 +141,15 @@ impl ConfigurationFolder {
                 .into_iter()
                 .find_map(|file| -> Option<ProxyYaml> {
                     from_str(&file.content)
+                        .map(|proxy| ProxyYaml {
+                            folder: file.path.parent().map(Path::to_path_buf),
+                            ..proxy
+                        })
                         .context(format!(
                             "Failed to decode api proxy '{}' in api folder '{api_path}'",
                             file.path.display()
                         ))
-                        .map_err(|e| error!("{:?}", e))
+                        .map_err(|e| self.skip(e))
                         .ok()
                 });
 
@@ -115,12 +162,12 @@ impl ConfigurationFolder {
         })
     }
 
-    pub(self) fn load_fs_system(fs_system: FsSystem) -> Result<SystemFolder> {
+    pub(self) fn load_fs_system(&self, fs_system: FsSystem) -> Result<SystemFolder> {
         let system_path = &fs_system.path.display();
         debug!("Loading system folder '{system_path}'");
 
-        let data = match fs_system.get_data_folder()? {
-            Some(fs_data) => ConfigurationFolder::load_fs_data(fs_data)?,
+        let mut data = match fs_system.get_data_folder()? {
+            Some(fs_data) => self.load_fs_data(fs_data)?,
             None => HashMap::new(),
         };
 
@@ -129,16 +176,16 @@ impl ConfigurationFolder {
             .into_iter()
             .filter_map(|fs_api| {
                 let fs_api_path = fs_api.path.display().to_string();
-                ConfigurationFolder::load_fs_api_folder(fs_api)
+                self.load_fs_api_folder(fs_api)
                     .context(format!(
                         "Failed to decode api folder '{fs_api_path}' in system folder '{system_path}'"
                     ))
-                    .map_err(|e| error!("{:?}", e))
+                    .map_err(|e| self.skip(e))
                     .ok()
             })
             .collect();
 
-        let apis: Vec<ApiYaml> = fs_system
+        let mut apis: Vec<ApiYaml> = fs_system
             .iter_api_files()?
             .into_iter()
             .filter_map(|file| -> Option<ApiYaml> {
@@ -148,23 +195,24 @@ impl ConfigurationFolder {
                         file.path.display(),
                         system_path
                     ))
-                    .map_err(|e| error!("{:?}", e))
+                    .map_err(|e| self.skip(e))
                     .ok()
+                    .map(|api| ConfigurationFolder::named_api(api, &file.path))
             })
             .collect();
 
-        let shape: Option<ApiShapeYaml> =
+        let mut shape: Option<ApiShapeYaml> =
             fs_system
                 .iter_shape_files()?
                 .into_iter()
                 .find_map(|file| -> Option<ApiShapeYaml> {
-                    from_str(&file.content)
+                    ConfigurationFolder::load_shape(&file.content, &file.path)
                         .context(format!(
                             "Failed to decode shape file '{}' in system folder '{}'",
                             file.path.display(),
                             system_path
                         ))
-                        .map_err(|e| error!("{:?}", e))
+                        .map_err(|e| self.skip(e))
                         .ok()
                 });
         let proxy: Option<ProxyYaml> =
@@ -173,17 +221,80 @@ impl ConfigurationFolder {
                 .into_iter()
                 .find_map(|file| -> Option<ProxyYaml> {
                     from_str(&file.content)
+                        .map(|proxy| ProxyYaml {
+                            folder: file.path.parent().map(Path::to_path_buf),
+                            ..proxy
+                        })
                         .context(format!(
                             "Failed to decode api proxy '{}' in api folder '{}'",
                             file.path.display(),
                             fs_system.path.display()
                         ))
-                        .map_err(|e| error!("{:?}", e))
+                        .map_err(|e| self.skip(e))
                         .ok()
                 });
 
+        let host: Option<HostYaml> =
+            fs_system
+                .iter_host_files()?
+                .into_iter()
+                .find_map(|file| -> Option<HostYaml> {
+                    from_str(&file.content)
+                        .context(format!(
+                            "Failed to decode host file '{}' in system folder '{}'",
+                            file.path.display(),
+                            system_path
+                        ))
+                        .map_err(|e| self.skip(e))
+                        .ok()
+                });
+
+        // The openapi document referenced by the shape is a contract, it isn't imported
+        let contract = shape
+            .as_ref()
+            .and_then(|shape| shape.openapi.as_ref())
+            .map(|openapi| fs_system.path.join(openapi));
+
+        let name = fs_system.get_name()?;
+        let imported: Vec<SystemFolder> = fs_system
+            .iter_openapi_files()?
+            .into_iter()
+            .filter(|file| Some(&file.path) != contract.as_ref())
+            .filter_map(|file| -> Option<SystemFolder> {
+                let mut system = import_openapi(&name, &file.content)
+                    .context(format!(
+                        "Failed to import openapi document '{}' in system folder '{}'",
+                        file.path.display(),
+                        system_path
+                    ))
+                    .map_err(|e| self.skip(e))
+                    .ok()?;
+                system.apis = system
+                    .apis
+                    .into_iter()
+                    .map(|api| ConfigurationFolder::named_api(api, &file.path))
+                    .collect();
+                Some(system)
+            })
+            .collect();
+
+        // The operations of a single openapi document are the shape of a system without shape
+        // file nor api file, the data files take precedence over the imported examples
+        if let [system] = imported.as_slice() {
+            if shape.is_none() && apis.is_empty() {
+                shape = system.shape.clone();
+            }
+        }
+        for system in imported {
+            apis.extend(system.apis);
+            for (key, value) in system.data {
+                data.entry(key).or_insert(value);
+            }
+        }
+
         Ok(SystemFolder {
-            name: fs_system.get_name()?,
+            name,
+            host,
             api_folders,
             apis,
             shape,
@@ -201,12 +312,32 @@ impl ConfigurationFolder {
                 .into_iter()
                 .filter_map(|system| {
                     let path = system.path.display().to_string();
-                    ConfigurationFolder::load_fs_system(system)
+                    self.load_fs_system(system)
                         .context(format!("Loading fs filesystem '{path}' in config"))
-                        .map_err(|err| error!("{:?}", err))
+                        .map_err(|e| self.skip(e))
                         .ok()
                 })
                 .collect(),
         })
     }
+    // Errors of the files skipped since the last call
+    pub fn take_skipped(&self) -> Vec<Error> {
+        self.skipped.take()
+    }
+    // Fails instead of skipping the files that can't be decoded, a configuration being updated
+    // in place is likely to be partially written
+    pub fn load_strictly_from_filesystem(&self) -> Result<ConfFolder> {
+        let conf = self.load_from_filesystem()?;
+
+        let skipped = self.take_skipped();
+        if !skipped.is_empty() {
+            bail!(
+                "{} invalid configuration file(s):\n{}",
+                skipped.len(),
+                skipped.iter().map(|e| format!("{e:?}")).join("\n")
+            );
+        }
+
+        Ok(conf)
+    }
 }
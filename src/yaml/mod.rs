@@ -1,55 +1,335 @@
 pub(crate) mod filesystem;
 pub(crate) mod from_files;
+pub(crate) mod openapi;
 pub(crate) mod to_domain;
+pub(crate) mod validate;
 
-use serde::Deserialize;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::value::{Tag, TaggedValue};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
+// Latencies are expressed in milliseconds, random profiles can be seeded to make a run reproducible
 #[derive(Deserialize, Clone, Debug)]
 pub enum LatencyYaml {
     Constant(u32),
+    Uniform {
+        min: u32,
+        max: u32,
+        seed: Option<u64>,
+    },
+    Normal {
+        mean: f64,
+        stddev: f64,
+        seed: Option<u64>,
+    },
+    LogNormal {
+        median: f64,
+        sigma: f64,
+        seed: Option<u64>,
+    },
+    Pareto {
+        scale: f64,
+        shape: f64,
+        seed: Option<u64>,
+    },
+    Percentiles {
+        min: Option<u32>,
+        p50: u32,
+        p90: u32,
+        p99: u32,
+        p999: u32,
+        seed: Option<u64>,
+    },
+}
+
+// Streams the response body in chunks: throughput in bytes per second, chunk size in bytes and
+// delay between chunks in milliseconds
+#[derive(Deserialize, Clone, Debug)]
+pub struct ThrottleYaml {
+    pub throughput: Option<u32>,
+    pub chunk_size: Option<u32>,
+    pub chunk_delay: Option<u32>,
+}
+
+// Failures injected on a percentage of the requests matching the rules of an api
+#[derive(Deserialize, Clone, Debug)]
+pub struct ChaosYaml {
+    pub seed: Option<u64>,
+    pub faults: Vec<ChaosFaultYaml>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ChaosFaultYaml {
+    pub percent: f64,
+    pub effect: ChaosEffectYaml,
+}
+
+// Status replaces the rule response, timeout waits the given milliseconds before answering 504
+#[derive(Deserialize, Clone, Debug)]
+pub enum ChaosEffectYaml {
+    Status(u16),
+    Timeout(u32),
+    CloseConnection,
+    MalformedHttp,
+    GarbageBytes,
+    Hang,
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub enum Response {
     File(String),
-    Inline(u16, Option<String>, Option<String>),
+    // Status, body, format and headers of the response, the headers can be left out
+    Inline(
+        u16,
+        Option<String>,
+        Option<String>,
+        #[serde(default)] Option<HashMap<String, String>>,
+    ),
     Ok,
     OkText(String),
     OkJson(String),
     OkXml(String),
+    // Network level faults
+    CloseConnection,
+    ResetMidBody(String),
+    MalformedHttp,
+    GarbageBytes,
+    Hang,
+    TruncatedContentLength(String),
+    // Responses returned in turn, stick-last by default
+    Sequence {
+        mode: Option<SequenceModeYaml>,
+        responses: Vec<Response>,
+    },
+    // Response drawn among weighted alternatives
+    OneOf(Vec<WeightedResponseYaml>),
 }
 
 #[derive(Deserialize, Clone, Debug)]
+pub struct WeightedResponseYaml {
+    pub weight: u32,
+    pub response: Response,
+}
+
+// Once: the rule stops matching after the last response, cycle: starts over after the last
+// response, stick-last: keeps returning the last response
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum SequenceModeYaml {
+    Once,
+    Cycle,
+    StickLast,
+}
+
+// Value matcher used by rule guards, either a plain string (exact match) or a tagged matcher:
+// "value", !Regex "^Bearer .+", !Prefix "Bearer ", !Present, !Absent, !Not "value", !Not { Regex: "^x" }
+#[derive(Clone, Debug)]
+pub enum MatcherYaml {
+    Exact(String),
+    Regex(String),
+    Prefix(String),
+    Present,
+    Absent,
+    Not(Box<MatcherYaml>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "MatcherYaml")]
+enum TaggedMatcherYaml {
+    Exact(String),
+    Regex(String),
+    Prefix(String),
+    Present,
+    Absent,
+    Not(Box<MatcherYaml>),
+}
+
+impl<'de> Deserialize<'de> for MatcherYaml {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tagged = match serde_yaml::Value::deserialize(deserializer)? {
+            serde_yaml::Value::String(value) => TaggedMatcherYaml::Exact(value),
+            serde_yaml::Value::Number(value) => TaggedMatcherYaml::Exact(value.to_string()),
+            serde_yaml::Value::Bool(value) => TaggedMatcherYaml::Exact(value.to_string()),
+            // Nested matchers can't be tagged twice in yaml, so { Regex: "^x" } is read as !Regex "^x"
+            serde_yaml::Value::Mapping(mapping) if mapping.len() == 1 => {
+                let (tag, value) = mapping.into_iter().next().unwrap();
+                let tag = tag
+                    .as_str()
+                    .ok_or(D::Error::custom("matcher name must be a string"))?;
+
+                TaggedMatcherYaml::deserialize(serde_yaml::Value::Tagged(Box::new(TaggedValue {
+                    tag: Tag::new(tag),
+                    value,
+                })))
+                .map_err(D::Error::custom)?
+            }
+            value => TaggedMatcherYaml::deserialize(value).map_err(D::Error::custom)?,
+        };
+
+        Ok(match tagged {
+            TaggedMatcherYaml::Exact(value) => MatcherYaml::Exact(value),
+            TaggedMatcherYaml::Regex(value) => MatcherYaml::Regex(value),
+            TaggedMatcherYaml::Prefix(value) => MatcherYaml::Prefix(value),
+            TaggedMatcherYaml::Present => MatcherYaml::Present,
+            TaggedMatcherYaml::Absent => MatcherYaml::Absent,
+            TaggedMatcherYaml::Not(matcher) => MatcherYaml::Not(matcher),
+        })
+    }
+}
+
+// Request body matchers: !Json and !JsonPartial hold a json document, !JsonPath and !XPath a query
+// that must select something, and !Regex a regular expression applied on the raw body
+#[derive(Deserialize, Clone, Debug)]
+pub enum BodyMatcherYaml {
+    Json(String),
+    JsonPartial(String),
+    JsonPath(String),
+    XPath(XPathYaml),
+    Regex(String),
+}
+
+// XPath query, with the namespaces of the prefixes it uses when the document has namespaces
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum XPathYaml {
+    Query(String),
+    Namespaced {
+        query: String,
+        namespaces: HashMap<String, String>,
+    },
+}
+
+// Serialized when recording proxied responses
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct ResponseDataYaml {
     pub status: u16,
+    #[allow(dead_code)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<String>,
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct ProxyYaml {
     pub url: String,
+    // Proxied requests and responses are written as rules and data files next to the proxy file
+    #[serde(default)]
+    pub record: bool,
+    // Requests matching no rule of the api set are forwarded under /static instead of answering 404
+    #[serde(default)]
+    pub fallback: bool,
+    // Changes to the headers of the forwarded requests and of the upstream responses
+    pub request_headers: Option<HeaderRulesYaml>,
+    pub response_headers: Option<HeaderRulesYaml>,
+    // Http client of the proxy, timeouts are expressed in milliseconds
+    pub connect_timeout: Option<u64>,
+    pub read_timeout: Option<u64>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub pool_idle_timeout: Option<u64>,
+    // Redirects followed before answering, 0 sends them back to the client
+    pub max_redirects: Option<usize>,
+    // Pem files relative to the proxy file: trusted certificates added to the system ones, and
+    // client certificate with its private key
+    pub ca_bundle: Option<String>,
+    pub client_certificate: Option<String>,
+    // Certificates of the upstream aren't verified, for self-signed local upstreams
+    #[serde(default)]
+    pub insecure: bool,
+    // Folder of the proxy file
+    #[serde(skip)]
+    pub folder: Option<PathBuf>,
+}
+
+// Added headers replace the forwarded values, rewrites replace the matches of a regex in a value
+#[derive(Deserialize, Clone, Debug)]
+pub struct HeaderRulesYaml {
+    pub add: Option<HashMap<String, String>>,
+    pub remove: Option<Vec<String>>,
+    pub rewrite: Option<HashMap<String, HeaderRewriteYaml>>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct HeaderRewriteYaml {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct HostYaml {
+    pub hosts: Vec<String>,
 }
 
 #[derive(Deserialize, Clone, Debug)]
 pub struct ApiYaml {
-    pub headers: Option<HashMap<String, String>>,
+    // Name of the api file without extension, or name given through the admin api
+    #[serde(skip)]
+    pub name: String,
+    // Path of the api file, none for the apis created through the admin api
+    #[serde(skip)]
+    pub path: Option<String>,
+    pub headers: Option<HashMap<String, MatcherYaml>>,
+    pub cookies: Option<HashMap<String, MatcherYaml>>,
+    pub host: Option<MatcherYaml>,
     pub latency: Option<LatencyYaml>,
+    pub throttle: Option<ThrottleYaml>,
+    pub chaos: Option<ChaosYaml>,
+    pub priority: Option<i32>,
     pub rules: Vec<RuleYaml>,
 }
 
+// Rules of a scenario only match in its required state, and move the scenario to its new state
+#[derive(Deserialize, Clone, Debug)]
+pub struct ScenarioYaml {
+    pub name: String,
+    pub required_state: Option<String>,
+    pub new_state: Option<String>,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct RuleYaml {
     pub matches: String,
+    pub query: Option<HashMap<String, MatcherYaml>>,
+    pub body: Option<Vec<BodyMatcherYaml>>,
+    pub priority: Option<i32>,
+    pub scenario: Option<ScenarioYaml>,
     pub latency: Option<LatencyYaml>,
+    pub throttle: Option<ThrottleYaml>,
+    // Headers added to the response, they take precedence over the headers of the data file
+    pub response_headers: Option<HashMap<String, String>>,
     pub response: Response,
 }
 
+// Expectation on the requests of the journal: the matching requests must have been received
+// exactly count times, or at least once without count
+#[derive(Deserialize, Clone, Debug)]
+pub struct VerificationYaml {
+    pub method: Option<String>,
+    pub path: Option<MatcherYaml>,
+    pub headers: Option<HashMap<String, MatcherYaml>>,
+    pub body: Option<Vec<BodyMatcherYaml>>,
+    pub count: Option<usize>,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct ApiShapeYaml {
+    // Endpoints of the apis, those of the openapi document when empty
+    #[serde(default)]
     pub shape: Vec<String>,
+    // Openapi document whose schemas the responses follow, relative to the shape file
+    pub openapi: Option<String>,
+    // Request bodies not following the schema of their operation are rejected
+    #[serde(default)]
+    pub validate_requests: bool,
+    // Content of the openapi document, read with the shape file
+    #[serde(skip)]
+    pub openapi_document: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -64,6 +344,7 @@ pub struct ApiFolder {
 #[derive(Clone, Debug)]
 pub struct SystemFolder {
     pub name: String,
+    pub host: Option<HostYaml>,
     pub api_folders: Vec<ApiFolder>,
     pub shape: Option<ApiShapeYaml>,
     pub proxy: Option<ProxyYaml>,
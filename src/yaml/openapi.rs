@@ -0,0 +1,490 @@
+use crate::core::{
+    ApiCore, ContractCore, EndpointCore, OperationContractCore, RequestSchemaCore, RuleBodyCore,
+};
+use crate::yaml::to_domain::extract_endpoint;
+use crate::yaml::{ApiShapeYaml, ApiYaml, Response, ResponseDataYaml, RuleYaml, SystemFolder};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use jsonschema::Validator;
+use openapiv3::{Example, MediaType, OpenAPI, Operation, ReferenceOr, Schema, StatusCode};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Schemas referencing themselves are only expanded up to this depth in generated examples
+const MAX_EXAMPLE_DEPTH: usize = 8;
+
+// Item of a reference to the components of the document, other documents are not followed
+fn resolve<'a, T>(
+    item: &'a ReferenceOr<T>,
+    components: Option<&'a IndexMap<String, ReferenceOr<T>>>,
+    prefix: &str,
+) -> Option<&'a T> {
+    match item {
+        ReferenceOr::Item(item) => Some(item),
+        ReferenceOr::Reference { reference } => {
+            components?.get(reference.strip_prefix(prefix)?)?.as_item()
+        }
+    }
+}
+
+// Example value of a json schema, built from its examples, defaults and types
+fn schema_example(schema: &Value, document: &Value, depth: usize) -> Value {
+    if depth > MAX_EXAMPLE_DEPTH {
+        return Value::Null;
+    }
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference
+            .strip_prefix('#')
+            .and_then(|pointer| document.pointer(pointer))
+            .map(|schema| schema_example(schema, document, depth + 1))
+            .unwrap_or_default();
+    }
+
+    if let Some(example) = schema.get("example").or(schema.get("default")) {
+        return example.clone();
+    }
+    if let Some(first) = schema.get("enum").and_then(|e| e.get(0)) {
+        return first.clone();
+    }
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        let mut merged = Map::new();
+        for schema in schemas {
+            if let Value::Object(properties) = schema_example(schema, document, depth + 1) {
+                merged.extend(properties);
+            }
+        }
+        return Value::Object(merged);
+    }
+    if let Some(first) = ["oneOf", "anyOf"]
+        .iter()
+        .find_map(|key| schema.get(key).and_then(|schemas| schemas.get(0)))
+    {
+        return schema_example(first, document, depth + 1);
+    }
+
+    match schema.get("type").and_then(Value::as_str) {
+        Some("array") => json!([schema
+            .get("items")
+            .map(|items| schema_example(items, document, depth + 1))
+            .unwrap_or_default()]),
+        Some("string") => match schema.get("format").and_then(Value::as_str) {
+            Some("date-time") => json!("2024-01-01T00:00:00Z"),
+            Some("date") => json!("2024-01-01"),
+            Some("uuid") => json!("00000000-0000-0000-0000-000000000000"),
+            Some("email") => json!("user@example.com"),
+            Some("uri") => json!("https://example.com"),
+            _ => json!("string"),
+        },
+        Some("integer") => schema.get("minimum").cloned().unwrap_or(json!(0)),
+        Some("number") => schema.get("minimum").cloned().unwrap_or(json!(0.0)),
+        Some("boolean") => json!(true),
+        Some("object") | None if schema.get("properties").is_some() => Value::Object(
+            schema["properties"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(name, property)| {
+                    (
+                        name.to_owned(),
+                        schema_example(property, document, depth + 1),
+                    )
+                })
+                .collect(),
+        ),
+        Some("object") => json!({}),
+        _ => Value::Null,
+    }
+}
+
+// Json media types are preferred, the declared example is preferred to the generated one
+fn media_example(
+    content: &IndexMap<String, MediaType>,
+    examples: Option<&IndexMap<String, ReferenceOr<Example>>>,
+    document: &Value,
+) -> Option<(String, Value)> {
+    let (format, media) = content
+        .iter()
+        .find(|(format, _)| format.contains("json"))
+        .or(content.first())?;
+
+    let example = media
+        .example
+        .clone()
+        .or_else(|| {
+            media.examples.values().find_map(|example| {
+                resolve(example, examples, "#/components/examples/")?
+                    .value
+                    .clone()
+            })
+        })
+        .or_else(|| {
+            let schema = serde_json::to_value(media.schema.as_ref()?).ok()?;
+            Some(schema_example(&schema, document, 0))
+        })?;
+
+    Some((format.to_owned(), example))
+}
+
+fn body(format: &str, example: Value) -> Result<String> {
+    Ok(match example {
+        Value::String(text) if !format.contains("json") => text,
+        example => serde_json::to_string_pretty(&example)?,
+    })
+}
+
+// Routes parameters are written {param} in openapi documents and :param in mochi
+fn route(path: &str) -> String {
+    path.replace('{', ":").replace('}', "")
+}
+
+fn data_key(method: &str, path: &str, operation: &Operation) -> String {
+    operation.operation_id.clone().unwrap_or_else(|| {
+        format!("{method}{path}")
+            .replace(['/', '{', '}'], "-")
+            .replace("--", "-")
+            .trim_end_matches('-')
+            .to_lowercase()
+    })
+}
+
+// Successful response of an operation, or its first response
+fn operation_response<'a>(
+    document: &'a OpenAPI,
+    operation: &'a Operation,
+) -> Option<(u16, &'a openapiv3::Response)> {
+    let responses = document.components.as_ref().map(|c| &c.responses);
+    let declared = operation
+        .responses
+        .responses
+        .iter()
+        .map(|(status, response)| {
+            let status = match status {
+                StatusCode::Code(code) => *code,
+                StatusCode::Range(range) => range * 100,
+            };
+            (status, response)
+        })
+        .collect::<Vec<_>>();
+
+    declared
+        .iter()
+        .find(|(status, _)| (200..300).contains(status))
+        .or(declared.first())
+        .copied()
+        .or(operation.responses.default.as_ref().map(|r| (200, r)))
+        .and_then(|(status, response)| {
+            Some((
+                status,
+                resolve(response, responses, "#/components/responses/")?,
+            ))
+        })
+}
+
+// System serving an example response for each operation of an openapi 3 document (in yaml or in
+// json), the operations being the shape of the system
+pub fn import_openapi(name: &str, content: &str) -> Result<SystemFolder> {
+    let document: OpenAPI = serde_yaml::from_str(content).context("Decoding openapi 3 document")?;
+    let raw: Value = serde_yaml::from_str(content).context("Decoding openapi 3 document")?;
+    let examples = document.components.as_ref().map(|c| &c.examples);
+
+    let mut shape = vec![];
+    let mut rules = vec![];
+    let mut data = HashMap::new();
+
+    for (path, method, operation) in document.operations() {
+        let method = method.to_ascii_uppercase();
+        let matches = format!("{method} {}", route(path));
+        let key = data_key(&method, path, operation);
+
+        let (status, response) = operation_response(&document, operation)
+            .context(format!("Finding a response of operation '{matches}'"))?;
+        let example = media_example(&response.content, examples, &raw);
+
+        data.insert(
+            key.to_owned(),
+            ResponseDataYaml {
+                status,
+                description: Some(response.description.to_owned()),
+                data: example
+                    .as_ref()
+                    .map(|(format, example)| body(format, example.clone()))
+                    .transpose()
+                    .context(format!(
+                        "Encoding example response of operation '{matches}'"
+                    ))?,
+                format: example.map(|(format, _)| format),
+                headers: None,
+            },
+        );
+
+        shape.push(matches.to_owned());
+        rules.push(RuleYaml {
+            matches,
+            query: None,
+            body: None,
+            priority: None,
+            scenario: None,
+            latency: None,
+            throttle: None,
+            response_headers: None,
+            response: Response::File(key),
+        });
+    }
+
+    Ok(SystemFolder {
+        name: name.to_owned(),
+        host: None,
+        api_folders: vec![],
+        shape: Some(ApiShapeYaml {
+            shape,
+            openapi: None,
+            validate_requests: false,
+            openapi_document: None,
+        }),
+        proxy: None,
+        apis: vec![ApiYaml {
+            name: "openapi".to_string(),
+            path: None,
+            headers: None,
+            cookies: None,
+            host: None,
+            latency: None,
+            throttle: None,
+            chaos: None,
+            priority: None,
+            rules,
+        }],
+        data,
+    })
+}
+
+// Rewrites the openapi 3.0 keywords of a schema and of its subschemas into json schema ones:
+// nullable schemas also accept null, and annotations unknown to json schema are dropped
+fn to_json_schema(schema: &mut Value) {
+    let Value::Object(object) = schema else {
+        return;
+    };
+    object.remove("example");
+    object.remove("discriminator");
+    for (keyword, value) in object.iter_mut() {
+        match (keyword.as_str(), value) {
+            ("properties" | "patternProperties", Value::Object(schemas)) => {
+                schemas.values_mut().for_each(to_json_schema)
+            }
+            ("allOf" | "anyOf" | "oneOf", Value::Array(schemas)) => {
+                schemas.iter_mut().for_each(to_json_schema)
+            }
+            ("items" | "not" | "additionalProperties", value) => to_json_schema(value),
+            _ => {}
+        }
+    }
+    if object.remove("nullable") != Some(Value::Bool(true)) {
+        return;
+    }
+    if let Some(Value::Array(values)) = object.get_mut("enum") {
+        values.push(Value::Null);
+    }
+    match object.get("type").cloned() {
+        Some(Value::String(kind)) => {
+            object.insert("type".to_string(), json!([kind, "null"]));
+        }
+        _ => *schema = json!({"anyOf": [schema.take(), {"type": "null"}]}),
+    }
+}
+
+// Validator of a schema of the document, which keeps the components of the document so the
+// schemas they reference can be resolved
+fn schema_validator(schema: &ReferenceOr<Schema>, document: &Value) -> Result<Arc<Validator>> {
+    let mut schema = serde_json::to_value(schema)?;
+    to_json_schema(&mut schema);
+    if let (Value::Object(schema), Some(components)) = (&mut schema, document.get("components")) {
+        let mut components = components.clone();
+        if let Some(Value::Object(schemas)) = components.get_mut("schemas") {
+            schemas.values_mut().for_each(to_json_schema);
+        }
+        schema.insert("components".to_string(), components);
+    }
+    Ok(Arc::new(
+        jsonschema::validator_for(&schema).context("Compiling json schema")?,
+    ))
+}
+
+fn json_schema(content: &IndexMap<String, MediaType>) -> Option<&ReferenceOr<Schema>> {
+    content
+        .iter()
+        .find(|(format, _)| format.contains("json"))
+        .and_then(|(_, media)| media.schema.as_ref())
+}
+
+// Operations of an openapi 3 document (in yaml or in json) with the json schemas of their request
+// and response bodies
+pub fn extract_contract(content: &str, validate_requests: bool) -> Result<ContractCore> {
+    let document: OpenAPI = serde_yaml::from_str(content).context("Decoding openapi 3 document")?;
+    let raw: Value = serde_yaml::from_str(content).context("Decoding openapi 3 document")?;
+    let components = document.components.as_ref();
+
+    let operations = document
+        .operations()
+        .map(|(path, method, operation)| {
+            let matches = format!("{} {}", method.to_ascii_uppercase(), route(path));
+
+            let request = operation
+                .request_body
+                .as_ref()
+                .and_then(|body| {
+                    resolve(
+                        body,
+                        components.map(|c| &c.request_bodies),
+                        "#/components/requestBodies/",
+                    )
+                })
+                .and_then(|body| Some((json_schema(&body.content)?, body.required)))
+                .map(|(schema, required)| {
+                    Ok::<_, anyhow::Error>(RequestSchemaCore {
+                        validator: schema_validator(schema, &raw)?,
+                        required,
+                    })
+                })
+                .transpose()
+                .context(format!("Building request schema of operation '{matches}'"))?;
+
+            let responses = operation
+                .responses
+                .responses
+                .iter()
+                .map(|(status, response)| (status.to_string(), response))
+                .chain(
+                    operation
+                        .responses
+                        .default
+                        .iter()
+                        .map(|response| ("default".to_string(), response)),
+                )
+                .filter_map(|(status, response)| {
+                    let response = resolve(
+                        response,
+                        components.map(|c| &c.responses),
+                        "#/components/responses/",
+                    )?;
+                    let schema = json_schema(&response.content)?;
+                    Some(
+                        schema_validator(schema, &raw)
+                            .map(|validator| (status.to_owned(), validator))
+                            .context(format!(
+                                "Building schema of response {status} of operation '{matches}'"
+                            )),
+                    )
+                })
+                .collect::<Result<HashMap<_, _>>>()?;
+
+            Ok(OperationContractCore {
+                endpoint: extract_endpoint(&matches)?,
+                request,
+                responses,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(ContractCore {
+        operations,
+        validate_requests,
+    })
+}
+
+// Errors of a schema validation, with the location of each error in the validated value
+fn schema_errors(validator: &Validator, value: &Value) -> Vec<String> {
+    validator
+        .iter_errors(value)
+        .map(|e| match e.instance_path().as_str() {
+            "" => e.to_string(),
+            path => format!("{e} at '{path}'"),
+        })
+        .collect()
+}
+
+pub fn request_schema_errors(
+    schema: &RequestSchemaCore,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Vec<String> {
+    // Bodies are optional unless the operation requires them, and only json bodies are checked
+    if body.is_empty() {
+        return match schema.required {
+            true => vec!["Request body is required".to_string()],
+            false => vec![],
+        };
+    }
+    if content_type.is_some_and(|content_type| !content_type.contains("json")) {
+        return vec![];
+    }
+    match serde_json::from_slice::<Value>(body) {
+        Ok(value) => schema_errors(&schema.validator, &value),
+        Err(e) => vec![format!("Request body is not valid json: {e}")],
+    }
+}
+
+impl ContractCore {
+    pub fn endpoints(&self) -> Vec<EndpointCore> {
+        self.operations
+            .iter()
+            .map(|operation| operation.endpoint.to_owned())
+            .collect()
+    }
+
+    // Plain json bodies of the responses of the api which don't follow the schema of their
+    // operation for their status, templated bodies are only known at request time
+    pub fn response_errors(&self, api: &ApiCore) -> Vec<String> {
+        let mut errors = vec![];
+        for rule in api.0.iter() {
+            let Some(operation) = self.operations.iter().find(|o| o.endpoint == rule.endpoint)
+            else {
+                continue;
+            };
+
+            for response in rule.response.responses() {
+                let status = response.status.as_u16();
+                let Some(validator) = [
+                    status.to_string(),
+                    format!("{}XX", status / 100),
+                    "default".to_string(),
+                ]
+                .iter()
+                .find_map(|key| operation.responses.get(key)) else {
+                    continue;
+                };
+                let Some(RuleBodyCore::Plain(body)) = &response.body else {
+                    continue;
+                };
+                if !response.format.contains("json") {
+                    continue;
+                }
+
+                let context = format!("Response {status} of rule '{}'", rule.endpoint);
+                match serde_json::from_str::<Value>(body) {
+                    Ok(value) => errors.extend(
+                        schema_errors(validator, &value)
+                            .into_iter()
+                            .map(|e| format!("{context} doesn't follow its schema: {e}")),
+                    ),
+                    Err(e) => errors.push(format!("{context} is not valid json: {e}")),
+                }
+            }
+        }
+        errors
+    }
+
+    pub fn attach_request_schemas(&self, api: &mut ApiCore) {
+        if !self.validate_requests {
+            return;
+        }
+        for rule in api.0.iter_mut() {
+            rule.request_schema = self
+                .operations
+                .iter()
+                .find(|o| o.endpoint == rule.endpoint)
+                .and_then(|operation| operation.request.clone());
+        }
+    }
+}
@@ -1,22 +1,40 @@
 use crate::core::{
-    ApiCore, ApiSetCore, ApiSetRootCore, ConfCore, EndpointCore, LatencyCore, ProxyCore, RuleCore,
-    SystemCore,
+    ApiCore, ApiSetCore, ApiSetRootCore, BodyMatcherCore, ChaosCore, ChaosEffectCore, ConfCore,
+    EndpointCore, FaultCore, HeaderRulesCore, LatencyCore, LatencyDistributionCore, MatcherCore,
+    ProxyCore, RandomCore, ResponseCore, RuleBodyCore, RuleCore, RuleResponseCore, ScenarioCore,
+    SequenceModeCore, SystemCore, ThrottleCore, VerificationCore,
 };
 use crate::template::render::rule_body_from_str;
+use crate::yaml::openapi::extract_contract;
 use crate::yaml::{
-    ApiShapeYaml, ApiYaml, ConfFolder, LatencyYaml, ProxyYaml, Response, ResponseDataYaml,
-    RuleYaml, SystemFolder,
+    ApiShapeYaml, ApiYaml, BodyMatcherYaml, ChaosEffectYaml, ChaosYaml, ConfFolder,
+    HeaderRulesYaml, LatencyYaml, MatcherYaml, ProxyYaml, Response, ResponseDataYaml, RuleYaml,
+    SequenceModeYaml, SystemFolder, ThrottleYaml, VerificationYaml, XPathYaml,
 };
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use axum::extract::Query;
 use axum::http::uri::PathAndQuery;
-use axum::http::{Method, StatusCode, Uri};
+use axum::http::{HeaderName, HeaderValue, Method, StatusCode, Uri};
+use axum::routing::MethodFilter;
 use itertools::Itertools;
+use rand::distributions::{Uniform, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rand_distr::{LogNormal, Normal, Pareto};
 use regex::Regex;
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Identity};
+use serde_json_path::JsonPath;
 use std::collections::{HashMap, LinkedList};
+use std::fs;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use sxd_xpath::Factory;
 
 // Parse endpoints like this "POST /route/to/my/endpoint"
-fn extract_endpoint(s: &String) -> Result<EndpointCore> {
+pub fn extract_endpoint(s: &String) -> Result<EndpointCore> {
     let regex_method_path: Regex = Regex::new(r"^(?<method>[A-Z]+)\s+(?<path>.+)$")?;
 
     let captured_result = regex_method_path.captures(s).context(format!(
@@ -24,22 +42,360 @@ fn extract_endpoint(s: &String) -> Result<EndpointCore> {
     ))?;
 
     let (_, [method_raw, path_raw]) = captured_result.extract();
+    let method = Method::from_str(method_raw)?;
+    // The router only serves the standard methods
+    MethodFilter::try_from(method.clone())
+        .map_err(|_| anyhow!("Unsupported method '{method_raw}' in endpoint '{s}'"))?;
+    if !path_raw.starts_with('/') {
+        bail!("Path of endpoint '{s}' should start with '/'");
+    }
 
     Ok(EndpointCore {
         route: PathAndQuery::from_str(path_raw)?,
-        method: Method::from_str(method_raw)?,
+        method,
     })
 }
 
-fn extract_rule(
-    rule: &RuleYaml,
-    api_latency: Option<LatencyYaml>,
-    api_headers: HashMap<String, String>,
-    data: HashMap<String, ResponseDataYaml>,
-) -> Result<RuleCore> {
-    let endpoint = extract_endpoint(&rule.matches)?;
+fn extract_matcher(matcher: &MatcherYaml) -> Result<MatcherCore> {
+    Ok(match matcher {
+        MatcherYaml::Exact(value) => MatcherCore::Exact(value.to_owned()),
+        MatcherYaml::Regex(value) => MatcherCore::Regex(
+            Regex::new(value).context(format!("Parsing matcher regex '{value}'"))?,
+        ),
+        MatcherYaml::Prefix(value) => MatcherCore::Prefix(value.to_owned()),
+        MatcherYaml::Present => MatcherCore::Present,
+        MatcherYaml::Absent => MatcherCore::Absent,
+        MatcherYaml::Not(inner) => MatcherCore::Not(Box::new(extract_matcher(inner)?)),
+    })
+}
+
+fn extract_body_matcher(matcher: &BodyMatcherYaml) -> Result<BodyMatcherCore> {
+    Ok(match matcher {
+        BodyMatcherYaml::Json(value) => BodyMatcherCore::Json(
+            serde_json::from_str(value).context(format!("Parsing body json '{value}'"))?,
+        ),
+        BodyMatcherYaml::JsonPartial(value) => BodyMatcherCore::JsonPartial(
+            serde_json::from_str(value).context(format!("Parsing body partial json '{value}'"))?,
+        ),
+        BodyMatcherYaml::JsonPath(value) => BodyMatcherCore::JsonPath(
+            JsonPath::parse(value).context(format!("Parsing body jsonpath '{value}'"))?,
+        ),
+        BodyMatcherYaml::XPath(xpath) => {
+            let (query, namespaces) = match xpath {
+                XPathYaml::Query(query) => (query, HashMap::new()),
+                XPathYaml::Namespaced { query, namespaces } => (query, namespaces.to_owned()),
+            };
+            // XPath expressions can't be shared between threads, they are only validated here
+            Factory::new()
+                .build(query)
+                .context(format!("Parsing body xpath '{query}'"))?
+                .context(format!("Body xpath '{query}' is empty"))?;
+            BodyMatcherCore::XPath {
+                query: query.to_owned(),
+                namespaces,
+            }
+        }
+        BodyMatcherYaml::Regex(value) => BodyMatcherCore::Regex(
+            Regex::new(value).context(format!("Parsing body regex '{value}'"))?,
+        ),
+    })
+}
+
+fn extract_matchers(
+    matchers: &Option<HashMap<String, MatcherYaml>>,
+    kind: &str,
+) -> Result<HashMap<String, MatcherCore>> {
+    matchers
+        .iter()
+        .flatten()
+        .map(|(key, matcher)| {
+            extract_matcher(matcher)
+                .context(format!("Extracting matcher of {kind} '{key}'"))
+                .map(|m| (key.to_owned(), m))
+        })
+        .collect()
+}
+
+pub fn extract_verification(verification: &VerificationYaml) -> Result<VerificationCore> {
+    Ok(VerificationCore {
+        method: verification
+            .method
+            .as_ref()
+            .map(|method| Method::from_str(method))
+            .transpose()
+            .context("Parsing verification method")?,
+        path: verification
+            .path
+            .as_ref()
+            .map(extract_matcher)
+            .transpose()
+            .context("Extracting verification path matcher")?,
+        // Header names of the journal are lowercase
+        headers: extract_matchers(&verification.headers, "header")?
+            .into_iter()
+            .map(|(name, matcher)| (name.to_ascii_lowercase(), matcher))
+            .collect(),
+        body_matchers: verification
+            .body
+            .iter()
+            .flatten()
+            .map(extract_body_matcher)
+            .collect::<Result<Vec<_>>>()
+            .context("Extracting verification body matchers")?,
+        count: verification.count,
+    })
+}
+
+pub fn extract_random(seed: &Option<u64>) -> RandomCore {
+    match seed {
+        Some(seed) => RandomCore::Seeded(Arc::new(Mutex::new(StdRng::seed_from_u64(*seed)))),
+        None => RandomCore::Thread,
+    }
+}
+
+fn extract_latency(latency: &LatencyYaml) -> Result<LatencyCore> {
+    let (distribution, seed) = match latency {
+        LatencyYaml::Constant(value) => return Ok(LatencyCore::Constant(*value)),
+        LatencyYaml::Uniform { min, max, seed } => {
+            if min > max {
+                bail!("Uniform latency min {min} is greater than max {max}");
+            }
+            (
+                LatencyDistributionCore::Uniform(Uniform::new_inclusive(*min as f64, *max as f64)),
+                seed,
+            )
+        }
+        LatencyYaml::Normal { mean, stddev, seed } => (
+            LatencyDistributionCore::Normal(
+                Normal::new(*mean, *stddev).context("Building normal latency")?,
+            ),
+            seed,
+        ),
+        LatencyYaml::LogNormal {
+            median,
+            sigma,
+            seed,
+        } => {
+            if *median <= 0.0 {
+                bail!("Log-normal latency median {median} must be positive");
+            }
+            (
+                LatencyDistributionCore::LogNormal(
+                    LogNormal::new(median.ln(), *sigma).context("Building log-normal latency")?,
+                ),
+                seed,
+            )
+        }
+        LatencyYaml::Pareto { scale, shape, seed } => (
+            LatencyDistributionCore::Pareto(
+                Pareto::new(*scale, *shape).context("Building pareto latency")?,
+            ),
+            seed,
+        ),
+        LatencyYaml::Percentiles {
+            min,
+            p50,
+            p90,
+            p99,
+            p999,
+            seed,
+        } => {
+            let points = vec![
+                (0.0, min.unwrap_or_default() as f64),
+                (0.5, *p50 as f64),
+                (0.9, *p90 as f64),
+                (0.99, *p99 as f64),
+                (0.999, *p999 as f64),
+                (1.0, *p999 as f64),
+            ];
+            if !points.windows(2).all(|w| w[0].1 <= w[1].1) {
+                bail!("Percentiles latency values must be increasing (min <= p50 <= p90 <= p99 <= p999)");
+            }
+            (LatencyDistributionCore::Percentiles(points), seed)
+        }
+    };
+
+    Ok(LatencyCore::Random {
+        distribution,
+        random: extract_random(seed),
+    })
+}
+
+fn extract_throttle(throttle: &ThrottleYaml) -> Result<ThrottleCore> {
+    if throttle.throughput == Some(0) {
+        bail!("Throttle throughput must be positive");
+    }
+    if throttle.chunk_size == Some(0) {
+        bail!("Throttle chunk size must be positive");
+    }
+
+    // Without explicit chunk size, the body is sent in about 10 chunks per second
+    let chunk_size = throttle
+        .chunk_size
+        .or(throttle.throughput.map(|t| (t / 10).max(1)))
+        .unwrap_or(1024);
+
+    Ok(ThrottleCore {
+        throughput: throttle.throughput,
+        chunk_size: chunk_size as usize,
+        chunk_delay: throttle.chunk_delay.unwrap_or_default(),
+    })
+}
+
+fn extract_chaos(chaos: &ChaosYaml) -> Result<ChaosCore> {
+    let mut total = 0.0;
+    let mut faults = vec![];
+
+    for fault in chaos.faults.iter() {
+        if !(0.0..=100.0).contains(&fault.percent) {
+            bail!(
+                "Chaos fault percent {} must be between 0 and 100",
+                fault.percent
+            );
+        }
+        total += fault.percent;
+
+        let effect = match fault.effect {
+            ChaosEffectYaml::Status(status) => ChaosEffectCore::Status(
+                StatusCode::from_u16(status).context(format!("Parsing chaos status '{status}'"))?,
+            ),
+            ChaosEffectYaml::Timeout(millis) => ChaosEffectCore::Timeout(millis),
+            ChaosEffectYaml::CloseConnection => ChaosEffectCore::Fault(FaultCore::CloseConnection),
+            ChaosEffectYaml::MalformedHttp => ChaosEffectCore::Fault(FaultCore::MalformedHttp),
+            ChaosEffectYaml::GarbageBytes => ChaosEffectCore::Fault(FaultCore::GarbageBytes),
+            ChaosEffectYaml::Hang => ChaosEffectCore::Fault(FaultCore::Hang),
+        };
+        faults.push((total, effect));
+    }
+
+    if total > 100.0 {
+        bail!("Chaos faults percents add up to {total}, more than 100");
+    }
+
+    Ok(ChaosCore {
+        faults,
+        random: extract_random(&chaos.seed),
+    })
+}
+
+// Header values are templates rendered like the response body
+fn extract_response_header(name: String, value: String) -> Result<(HeaderName, RuleBodyCore)> {
+    let header_name =
+        HeaderName::from_str(&name).context(format!("Parsing response header name '{name}'"))?;
+    let header_value = rule_body_from_str(value);
+    if let RuleBodyCore::Plain(value) = &header_value {
+        HeaderValue::from_str(value).context(format!(
+            "Parsing value '{value}' of response header '{name}'"
+        ))?;
+    }
+    Ok((header_name, header_value))
+}
+
+fn extract_header_rules(rules: &Option<HeaderRulesYaml>) -> Result<HeaderRulesCore> {
+    let Some(rules) = rules else {
+        return Ok(HeaderRulesCore::default());
+    };
+    let header_name =
+        |name: &String| HeaderName::from_str(name).context(format!("Parsing header name '{name}'"));
+
+    Ok(HeaderRulesCore {
+        add: rules
+            .add
+            .iter()
+            .flatten()
+            .map(|(name, value)| {
+                Ok((
+                    header_name(name)?,
+                    HeaderValue::from_str(value)
+                        .context(format!("Parsing value '{value}' of header '{name}'"))?,
+                ))
+            })
+            .collect::<Result<_>>()?,
+        remove: rules
+            .remove
+            .iter()
+            .flatten()
+            .map(header_name)
+            .collect::<Result<_>>()?,
+        rewrite: rules
+            .rewrite
+            .iter()
+            .flatten()
+            .map(|(name, rewrite)| {
+                Ok((
+                    header_name(name)?,
+                    Regex::new(&rewrite.pattern).context(format!(
+                        "Parsing rewrite regex '{}' of header '{name}'",
+                        rewrite.pattern
+                    ))?,
+                    rewrite.replacement.to_owned(),
+                ))
+            })
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn extract_proxy_client(proxy: &ProxyYaml) -> Result<reqwest::Client> {
+    let read_pem = |file: &String| {
+        let path = match &proxy.folder {
+            Some(folder) => folder.join(file),
+            None => PathBuf::from(file),
+        };
+        fs::read(&path).context(format!("Reading pem file '{}'", path.display()))
+    };
+
+    let mut builder = reqwest::Client::builder().tls_danger_accept_invalid_certs(proxy.insecure);
+    if let Some(timeout) = proxy.connect_timeout {
+        builder = builder.connect_timeout(Duration::from_millis(timeout));
+    }
+    if let Some(timeout) = proxy.read_timeout {
+        builder = builder.read_timeout(Duration::from_millis(timeout));
+    }
+    if let Some(max) = proxy.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max);
+    }
+    if let Some(timeout) = proxy.pool_idle_timeout {
+        builder = builder.pool_idle_timeout(Duration::from_millis(timeout));
+    }
+    if let Some(max) = proxy.max_redirects {
+        builder = builder.redirect(match max {
+            0 => Policy::none(),
+            max => Policy::limited(max),
+        });
+    }
+    if let Some(file) = &proxy.ca_bundle {
+        let certificates = Certificate::from_pem_bundle(&read_pem(file)?)
+            .context(format!("Parsing ca bundle '{file}'"))?;
+        builder = builder.tls_certs_merge(certificates);
+    }
+    if let Some(file) = &proxy.client_certificate {
+        let identity = Identity::from_pem(&read_pem(file)?)
+            .context(format!("Parsing client certificate '{file}'"))?;
+        builder = builder.identity(identity);
+    }
+
+    builder.build().context("Building http client")
+}
 
-    let (real_status, opt_body, opt_format) = match rule.response.clone() {
+pub fn extract_proxy(proxy: &ProxyYaml) -> Result<ProxyCore> {
+    Ok(ProxyCore {
+        url: Uri::try_from(proxy.url.clone()).context(format!("Parsing url '{}'", proxy.url))?,
+        record: proxy.folder.clone().filter(|_| proxy.record),
+        fallback: proxy.fallback,
+        request_headers: extract_header_rules(&proxy.request_headers)
+            .context("Extracting request headers")?,
+        response_headers: extract_header_rules(&proxy.response_headers)
+            .context("Extracting response headers")?,
+        client: extract_proxy_client(proxy).context("Extracting http client")?,
+    })
+}
+
+fn extract_response(
+    response: &Response,
+    rule_headers: &Option<HashMap<String, String>>,
+    data: &HashMap<String, ResponseDataYaml>,
+) -> Result<ResponseCore> {
+    let (real_status, opt_body, opt_format, own_headers) = match response.clone() {
         Response::File(path) => {
             let file = data
                 .get(&path)
@@ -51,63 +407,281 @@ fn extract_rule(
                     .clone()
                     .and_then(|b| if b.is_empty() { None } else { Some(b) }),
                 file.format.clone(),
+                file.headers.clone(),
             )
         }
-        Response::Inline(status, body, format) => (
+        Response::Inline(status, body, format, headers) => (
             StatusCode::from_u16(status).context(format!("Parsing file status '{status}'"))?,
             body.and_then(|b| if b.is_empty() { None } else { Some(b) }),
             format,
+            headers,
+        ),
+        Response::OkText(body) => (
+            StatusCode::OK,
+            Some(body),
+            Some("text/plain".to_string()),
+            None,
         ),
-        Response::OkText(body) => (StatusCode::OK, Some(body), Some("text/plain".to_string())),
         Response::OkJson(body) => (
             StatusCode::OK,
             Some(body),
             Some("application/json".to_string()),
+            None,
         ),
         Response::OkXml(body) => (
             StatusCode::OK,
             Some(body),
             Some("application/xml".to_string()),
+            None,
         ),
-        Response::Ok => (StatusCode::NO_CONTENT, None, None),
+        Response::Ok => (StatusCode::NO_CONTENT, None, None, None),
+        Response::ResetMidBody(body) | Response::TruncatedContentLength(body) => {
+            (StatusCode::OK, Some(body), None, None)
+        }
+        Response::CloseConnection
+        | Response::MalformedHttp
+        | Response::GarbageBytes
+        | Response::Hang => (StatusCode::OK, None, None, None),
+        Response::Sequence { .. } | Response::OneOf(_) => {
+            bail!("Sequences and alternatives of responses can't be nested")
+        }
+    };
+
+    let fault = match response {
+        Response::CloseConnection => Some(FaultCore::CloseConnection),
+        Response::ResetMidBody(_) => Some(FaultCore::ResetMidBody),
+        Response::MalformedHttp => Some(FaultCore::MalformedHttp),
+        Response::GarbageBytes => Some(FaultCore::GarbageBytes),
+        Response::Hang => Some(FaultCore::Hang),
+        Response::TruncatedContentLength(_) => Some(FaultCore::TruncatedContentLength),
+        _ => None,
     };
 
     let opt_rule_body = opt_body.map(rule_body_from_str);
 
+    // Headers of the rule apply to all its responses, after those of data files but before those
+    // of inline responses that may differ from one response of a sequence to the other
+    let (file_headers, inline_headers) = match response {
+        Response::Inline(..) => (None, own_headers),
+        _ => (own_headers, None),
+    };
+    let response_headers = file_headers
+        .into_iter()
+        .flatten()
+        .chain(
+            rule_headers
+                .iter()
+                .flatten()
+                .map(|(k, v)| (k.to_owned(), v.to_owned())),
+        )
+        .chain(inline_headers.into_iter().flatten())
+        .collect::<HashMap<_, _>>()
+        .into_iter()
+        .sorted()
+        .map(|(name, value)| extract_response_header(name, value))
+        .collect::<Result<Vec<_>>>()
+        .context("Extracting response headers")?;
+
+    Ok(ResponseCore {
+        status: real_status,
+        format: opt_format.unwrap_or(String::from("text/plain")),
+        headers: response_headers,
+        body: opt_rule_body,
+        fault,
+    })
+}
+
+// Api level settings shared by all the rules of an api
+pub struct ApiDefaults {
+    name: String,
+    // Api set and name of the api, identifying its sequences of responses across extractions
+    scope: String,
+    source: Option<String>,
+    latency: Option<LatencyCore>,
+    throttle: Option<ThrottleCore>,
+    chaos: Option<ChaosCore>,
+    priority: Option<i32>,
+    headers: HashMap<String, MatcherCore>,
+    cookies: HashMap<String, MatcherCore>,
+    host: Option<MatcherCore>,
+}
+
+// Rules of the api matching the same endpoint before the rule at the given position. With the
+// endpoint it identifies the rule, unlike its position which changes when other rules are removed
+pub fn occurrence(rules: &[RuleYaml], pos: usize) -> usize {
+    rules[..pos]
+        .iter()
+        .filter(|rule| rule.matches == rules[pos].matches)
+        .count()
+}
+
+pub fn extract_rule(
+    rule: &RuleYaml,
+    occurrence: usize,
+    api_defaults: &ApiDefaults,
+    data: HashMap<String, ResponseDataYaml>,
+) -> Result<RuleCore> {
+    let EndpointCore { route, method } = extract_endpoint(&rule.matches)?;
+
+    // Query parameters of the matched route are exact matchers, explicit query matchers take precedence
+    let Query(route_query): Query<HashMap<String, String>> =
+        Query::try_from_uri(&Uri::from(route.clone()))
+            .context(format!("Parsing query parameters of '{}'", rule.matches))?;
+
+    let query = route_query
+        .into_iter()
+        .map(|(key, value)| (key, MatcherCore::Exact(value)))
+        .chain(extract_matchers(&rule.query, "query parameter")?)
+        .collect();
+
+    let endpoint = EndpointCore {
+        route: PathAndQuery::from_str(route.path())?,
+        method,
+    };
+
+    let body_matchers = rule
+        .body
+        .iter()
+        .flatten()
+        .map(extract_body_matcher)
+        .collect::<Result<Vec<_>>>()
+        .context(format!("Extracting body matchers of '{}'", rule.matches))?;
+
+    let response = match &rule.response {
+        Response::Sequence { mode, responses } => {
+            if responses.is_empty() {
+                bail!("Sequence of responses of '{}' is empty", rule.matches);
+            }
+            RuleResponseCore::Sequence {
+                id: format!("{}#{}#{occurrence}", api_defaults.scope, rule.matches),
+                mode: match mode.unwrap_or(SequenceModeYaml::StickLast) {
+                    SequenceModeYaml::Once => SequenceModeCore::Once,
+                    SequenceModeYaml::Cycle => SequenceModeCore::Cycle,
+                    SequenceModeYaml::StickLast => SequenceModeCore::StickLast,
+                },
+                responses: responses
+                    .iter()
+                    .enumerate()
+                    .map(|(pos, response)| {
+                        extract_response(response, &rule.response_headers, &data)
+                            .context(format!("Extracting response {} of the sequence", pos + 1))
+                    })
+                    .collect::<Result<Vec<_>>>()
+                    .context(format!("Extracting responses of '{}'", rule.matches))?,
+            }
+        }
+        Response::OneOf(alternatives) => RuleResponseCore::OneOf {
+            weights: WeightedIndex::new(alternatives.iter().map(|a| a.weight)).context(format!(
+                "Building weights of the alternative responses of '{}'",
+                rule.matches
+            ))?,
+            responses: alternatives
+                .iter()
+                .enumerate()
+                .map(|(pos, alternative)| {
+                    extract_response(&alternative.response, &rule.response_headers, &data)
+                        .context(format!("Extracting alternative response {}", pos + 1))
+                })
+                .collect::<Result<Vec<_>>>()
+                .context(format!("Extracting responses of '{}'", rule.matches))?,
+        },
+        response => RuleResponseCore::Single(Box::new(
+            extract_response(response, &rule.response_headers, &data)
+                .context(format!("Extracting response of '{}'", rule.matches))?,
+        )),
+    };
+
     Ok(RuleCore {
+        api: api_defaults.name.to_owned(),
+        source: api_defaults.source.to_owned(),
         endpoint,
-        headers: api_headers,
+        headers: api_defaults.headers.clone(),
+        cookies: api_defaults.cookies.clone(),
+        host: api_defaults.host.clone(),
+        query,
+        body_matchers,
+        scenario: rule.scenario.as_ref().map(|scenario| ScenarioCore {
+            name: scenario.name.to_owned(),
+            required_state: scenario.required_state.to_owned(),
+            new_state: scenario.new_state.to_owned(),
+        }),
         latency: rule
             .latency
-            .clone()
-            .or(api_latency)
-            .map(|latency| match latency {
-                LatencyYaml::Constant(value) => LatencyCore::Constant(value),
-            }),
-        status: real_status,
-        format: opt_format.unwrap_or(String::from("text/plain")),
-        body: opt_rule_body,
+            .as_ref()
+            .map(extract_latency)
+            .transpose()
+            .context(format!("Extracting latency of '{}'", rule.matches))?
+            .or(api_defaults.latency.clone()),
+        throttle: rule
+            .throttle
+            .as_ref()
+            .map(extract_throttle)
+            .transpose()
+            .context(format!("Extracting throttle of '{}'", rule.matches))?
+            .or(api_defaults.throttle.clone()),
+        chaos: api_defaults.chaos.clone(),
+        priority: rule.priority.or(api_defaults.priority).unwrap_or_default(),
+        request_schema: None,
+        response,
+    })
+}
+
+pub fn extract_api_defaults(api: &ApiYaml) -> Result<ApiDefaults> {
+    Ok(ApiDefaults {
+        name: api.name.to_owned(),
+        scope: api.name.to_owned(),
+        source: api.path.to_owned(),
+        latency: api
+            .latency
+            .as_ref()
+            .map(extract_latency)
+            .transpose()
+            .context("Extracting api latency")?,
+        throttle: api
+            .throttle
+            .as_ref()
+            .map(extract_throttle)
+            .transpose()
+            .context("Extracting api throttle")?,
+        chaos: api
+            .chaos
+            .as_ref()
+            .map(extract_chaos)
+            .transpose()
+            .context("Extracting api chaos")?,
+        priority: api.priority,
+        headers: extract_matchers(&api.headers, "header")?,
+        cookies: extract_matchers(&api.cookies, "cookie")?,
+        host: api
+            .host
+            .as_ref()
+            .map(extract_matcher)
+            .transpose()
+            .context("Extracting host matcher")?,
     })
 }
 
-fn extract_api(api: &ApiYaml, data: &HashMap<String, ResponseDataYaml>) -> Result<ApiCore> {
+fn extract_api(
+    api: &ApiYaml,
+    api_set: &str,
+    data: &HashMap<String, ResponseDataYaml>,
+) -> Result<ApiCore> {
+    let api_defaults = ApiDefaults {
+        scope: format!("{api_set}/{}", api.name),
+        ..extract_api_defaults(api)?
+    };
+
     let extracted_rules: Result<Vec<RuleCore>> = api
         .rules
         .iter()
-        .map(|r| {
-            extract_rule(
-                r,
-                api.latency.clone(),
-                api.headers.clone().unwrap_or_default(),
-                data.clone(),
-            )
-        })
+        .enumerate()
+        .map(|(pos, r)| extract_rule(r, occurrence(&api.rules, pos), &api_defaults, data.clone()))
         .collect();
 
     Ok(ApiCore(extracted_rules?))
 }
 
-fn extract_api_shape(api_shape: &ApiShapeYaml) -> Result<Vec<EndpointCore>> {
+pub fn extract_api_shape(api_shape: &ApiShapeYaml) -> Result<Vec<EndpointCore>> {
     let extracted_endpoints: Result<Vec<EndpointCore>> =
         api_shape.shape.iter().map(extract_endpoint).collect();
 
@@ -121,36 +695,21 @@ pub fn build_api_set(
     proxy: &Option<ProxyYaml>,
     data: &HashMap<String, ResponseDataYaml>,
 ) -> Result<ApiSetCore> {
-    let apis_core: Vec<ApiCore> = apis
+    let mut apis_core: Vec<ApiCore> = apis
         .iter()
-        .map(|api| extract_api(api, data))
+        .map(|api| extract_api(api, name, data))
         .collect::<Result<Vec<_>>>()?;
 
-    let proxy_core = match proxy {
-        Some(p) => {
-            let uri = Uri::try_from(p.url.clone()).context(format!(
-                "Parsing url '{}' while building api_set '{name}'",
-                p.url
-            ))?;
-            Some(ProxyCore(uri))
-        }
-        None => None,
-    };
+    let proxy_core = proxy
+        .as_ref()
+        .map(|p| extract_proxy(p).context(format!("Extracting proxy of api_set '{name}'")))
+        .transpose()?;
 
-    let shape_core = match shape {
-        Some(s) => {
-            let shape = extract_api_shape(s).context(format!(
-                "Extracting api shape while building api_set '{name}'"
-            ))?;
-            for (pos, api) in apis_core.iter().enumerate() {
-                validate_api_with_shape(name, &shape, api).context(format!(
-                    "Validating api '{pos}' against shape while building api_set '{name}'"
-                ))?
-            }
-            Some(shape)
-        }
-        None => None,
-    };
+    let shape_core = shape
+        .as_ref()
+        .map(|s| apply_shape(name, s, &mut apis_core))
+        .transpose()
+        .context(format!("Applying shape while building api_set '{name}'"))?;
 
     Ok(ApiSetCore {
         name: name.to_owned(),
@@ -160,12 +719,55 @@ pub fn build_api_set(
     })
 }
 
+// The apis of an api set implement the endpoints of its shape, and their responses follow the
+// schemas of its openapi document
+pub fn apply_shape(
+    name: &String,
+    shape: &ApiShapeYaml,
+    apis: &mut [ApiCore],
+) -> Result<Vec<EndpointCore>> {
+    let contract = shape
+        .openapi_document
+        .as_ref()
+        .map(|document| extract_contract(document, shape.validate_requests))
+        .transpose()
+        .context(format!(
+            "Extracting openapi document '{}'",
+            shape.openapi.as_deref().unwrap_or_default()
+        ))?;
+
+    let endpoints = match &contract {
+        Some(contract) if shape.shape.is_empty() => contract.endpoints(),
+        _ => extract_api_shape(shape).context("Extracting api shape")?,
+    };
+
+    for (pos, api) in apis.iter_mut().enumerate() {
+        validate_api_with_shape(name, &endpoints, api)
+            .context(format!("Validating api '{pos}' against shape"))?;
+
+        if let Some(contract) = &contract {
+            let errors = contract.response_errors(api);
+            if !errors.is_empty() {
+                bail!(
+                    "Api name: {name}\n -> Responses don't follow the openapi document:\n - {}",
+                    errors.join("\n - ")
+                );
+            }
+            contract.attach_request_schemas(api);
+        }
+    }
+
+    Ok(endpoints)
+}
+
 pub fn validate_api_with_shape(name: &String, shape: &[EndpointCore], api: &ApiCore) -> Result<()> {
-    if shape.len() != api.0.len() {
+    // Several rules can implement the same endpoint with different query guards
+    let api_endpoints_count = api.0.iter().map(|rule| &rule.endpoint).unique().count();
+    if shape.len() != api_endpoints_count {
         bail!(
             "Api name: {name}\n -> Shape and api don’t have the same number of endpoints: {} != {}",
             shape.len(),
-            api.0.len()
+            api_endpoints_count
         );
     }
 
@@ -181,9 +783,7 @@ pub fn validate_api_with_shape(name: &String, shape: &[EndpointCore], api: &ApiC
 
     // Checking all api rules are present in the shape definition
     for el in api.0.iter() {
-        let api_rule_present_in_shape = shape
-            .iter()
-            .any(|shape_endpoint| *shape_endpoint == el.endpoint);
+        let api_rule_present_in_shape = shape.contains(&el.endpoint);
 
         if !api_rule_present_in_shape {
             messages.push_front(format!(
@@ -206,37 +806,30 @@ pub fn validate_api_with_shape(name: &String, shape: &[EndpointCore], api: &ApiC
 pub fn build_root_api_set(system: &SystemFolder) -> Result<ApiSetRootCore> {
     let system_name = &system.name;
 
-    let apis_core: Vec<ApiCore> = system
+    let mut apis_core: Vec<ApiCore> = system
         .apis
         .iter()
-        .map(|api| extract_api(api, &system.data))
+        .map(|api| extract_api(api, "", &system.data))
         .collect::<Result<Vec<_>>>()?;
 
-    let proxy_core = match &system.proxy {
-        Some(p) => {
-            let uri = Uri::try_from(p.url.clone()).context(format!(
-                "Parsing url '{}' while building root api_set of system '{system_name}'",
-                p.url
-            ))?;
-            Some(ProxyCore(uri))
-        }
-        None => None,
-    };
+    let proxy_core = system
+        .proxy
+        .as_ref()
+        .map(|p| {
+            extract_proxy(p).context(format!(
+                "Extracting proxy of root api_set of system '{system_name}'"
+            ))
+        })
+        .transpose()?;
 
-    let shape_core = match &system.shape {
-        Some(s) => {
-            let shape = extract_api_shape(s).context(format!(
-                "Extracting api shape while building root api_set of system '{system_name}'"
-            ))?;
-            for (pos, api) in apis_core.iter().enumerate() {
-                validate_api_with_shape(&system.name, &shape, api).context(format!(
-                    "Validating api '{pos}' against shape while building root api_set of system '{system_name}'"
-                ))?
-            }
-            Some(shape)
-        }
-        None => None,
-    };
+    let shape_core = system
+        .shape
+        .as_ref()
+        .map(|s| apply_shape(system_name, s, &mut apis_core))
+        .transpose()
+        .context(format!(
+            "Applying shape while building root api_set of system '{system_name}'"
+        ))?;
 
     Ok(ApiSetRootCore {
         shape: shape_core,
@@ -269,6 +862,16 @@ impl ConfFolder {
                     .collect::<Result<Vec<_>>>()?;
                 Ok(SystemCore {
                     name: system.name.to_owned(),
+                    hosts: system
+                        .host
+                        .as_ref()
+                        .map(|h| {
+                            h.hosts
+                                .iter()
+                                .map(|host| host.to_ascii_lowercase())
+                                .collect()
+                        })
+                        .unwrap_or_default(),
                     root_api_set,
                     api_sets,
                 })
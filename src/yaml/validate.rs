@@ -0,0 +1,218 @@
+use crate::core::ApiCore;
+use crate::yaml::to_domain::{
+    apply_shape, extract_api_defaults, extract_proxy, extract_rule, occurrence,
+};
+use crate::yaml::{ApiShapeYaml, ApiYaml, ConfFolder, ProxyYaml, ResponseDataYaml};
+use anyhow::{Context, Error};
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+// Step of the path to a node of a yaml document
+enum Step<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+// Walks a yaml document down to a node, and fails there. The parser gives the position of the node
+// it was visiting to the errors it reports, the error raised on the node carries its line
+struct Locator<'a>(&'a [Step<'a>]);
+
+const LOCATED: &str = "located node";
+
+impl<'de> DeserializeSeed<'de> for Locator<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Locator<'_> {
+    type Value = ();
+
+    // Nodes reached are refused for not being the expected type, which raises the located error
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(LOCATED)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let [Step::Key(key), rest @ ..] = self.0 else {
+            return Err(de::Error::invalid_type(de::Unexpected::Map, &self));
+        };
+        while let Some(name) = map.next_key::<Value>()? {
+            if name.as_str() == Some(key) {
+                return map.next_value_seed(Locator(rest));
+            }
+            map.next_value::<IgnoredAny>()?;
+        }
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let [Step::Index(index), rest @ ..] = self.0 else {
+            return Err(de::Error::invalid_type(de::Unexpected::Seq, &self));
+        };
+        for _ in 0..*index {
+            if seq.next_element::<IgnoredAny>()?.is_none() {
+                return Ok(());
+            }
+        }
+        seq.next_element_seed(Locator(rest))?;
+        Ok(())
+    }
+}
+
+// Line of a node of a yaml document, none when the document has no such node
+fn node_line(content: &str, path: &[Step]) -> Option<usize> {
+    match Locator(path).deserialize(serde_yaml::Deserializer::from_str(content)) {
+        Err(e) if e.to_string().contains(LOCATED) => e.location().map(|l| l.line()),
+        _ => None,
+    }
+}
+
+// Api level keys, in the order they are extracted
+const API_KEYS: [&str; 6] = ["latency", "throttle", "chaos", "headers", "cookies", "host"];
+
+impl ApiYaml {
+    fn location(&self) -> String {
+        match &self.path {
+            Some(path) => format!("api file '{path}'"),
+            None => format!("api '{}'", self.name),
+        }
+    }
+
+    fn line(&self, path: &[Step]) -> Option<usize> {
+        node_line(&fs::read_to_string(self.path.as_ref()?).ok()?, path)
+    }
+
+    // Copy of the api keeping only one of its api level keys
+    fn only(&self, key: &str) -> ApiYaml {
+        ApiYaml {
+            latency: self.latency.clone().filter(|_| key == "latency"),
+            throttle: self.throttle.clone().filter(|_| key == "throttle"),
+            chaos: self.chaos.clone().filter(|_| key == "chaos"),
+            headers: self.headers.clone().filter(|_| key == "headers"),
+            cookies: self.cookies.clone().filter(|_| key == "cookies"),
+            host: self.host.clone().filter(|_| key == "host"),
+            rules: vec![],
+            ..self.clone()
+        }
+    }
+
+    // Api level key failing the extraction of the api defaults
+    fn invalid_key(&self) -> Option<&'static str> {
+        API_KEYS
+            .into_iter()
+            .find(|key| extract_api_defaults(&self.only(key)).is_err())
+    }
+}
+
+// Validates each rule of each api of an api set, then the apis against the shape of the api set
+fn validate_api_set(
+    name: &str,
+    shape: &Option<ApiShapeYaml>,
+    apis: &[ApiYaml],
+    proxy: &Option<ProxyYaml>,
+    data: &HashMap<String, ResponseDataYaml>,
+) -> Vec<Error> {
+    let mut errors = vec![];
+
+    if let Some(proxy) = proxy {
+        if let Err(e) = extract_proxy(proxy) {
+            errors.push(e.context(format!("Extracting proxy of api set '{name}'")));
+        }
+    }
+
+    let mut valid_apis = vec![];
+    for api in apis {
+        let defaults = match extract_api_defaults(api) {
+            Ok(defaults) => defaults,
+            Err(e) => {
+                let line = api
+                    .invalid_key()
+                    .and_then(|key| Some((key, api.line(&[Step::Key(key)])?)));
+                errors.push(e.context(match line {
+                    Some((key, line)) => format!("In '{key}' at line {line} of {}", api.location()),
+                    None => format!("In {}", api.location()),
+                }));
+                continue;
+            }
+        };
+
+        let mut rules = vec![];
+        for (pos, rule) in api.rules.iter().enumerate() {
+            match extract_rule(rule, occurrence(&api.rules, pos), &defaults, data.clone()) {
+                Ok(rule) => rules.push(rule),
+                Err(e) => errors.push(e.context(
+                    match api.line(&[Step::Key("rules"), Step::Index(pos)]) {
+                        Some(line) => format!(
+                            "In rule '{}' at line {line} of {}",
+                            rule.matches,
+                            api.location()
+                        ),
+                        None => format!("In rule '{}' of {}", rule.matches, api.location()),
+                    },
+                )),
+            }
+        }
+
+        // Apis with invalid rules can't be checked against the shape
+        if rules.len() == api.rules.len() {
+            valid_apis.push((api, ApiCore(rules)));
+        }
+    }
+
+    // The shape is checked on its own first, so its errors are only reported once
+    if let Some(shape) = shape {
+        match apply_shape(&name.to_owned(), shape, &mut []) {
+            Ok(_) => errors.extend(valid_apis.into_iter().filter_map(|(api, mut core)| {
+                apply_shape(&name.to_owned(), shape, std::slice::from_mut(&mut core))
+                    .context(format!(
+                        "Validating {} against the shape of api set '{name}'",
+                        api.location()
+                    ))
+                    .err()
+            })),
+            Err(e) => errors.push(e.context(format!("Extracting shape of api set '{name}'"))),
+        }
+    }
+
+    errors
+}
+
+impl ConfFolder {
+    // Unlike the extraction, which stops at the first error, all the errors are gathered
+    pub fn validate(&self) -> Vec<Error> {
+        self.systems
+            .iter()
+            .flat_map(|system| {
+                let root = validate_api_set(
+                    &system.name,
+                    &system.shape,
+                    &system.apis,
+                    &system.proxy,
+                    &system.data,
+                );
+                let folders = system.api_folders.iter().flat_map(|folder| {
+                    let data = folder
+                        .data
+                        .clone()
+                        .into_iter()
+                        .chain(system.data.clone())
+                        .collect();
+                    validate_api_set(
+                        &folder.name,
+                        &folder.shape,
+                        &folder.apis,
+                        &folder.proxy,
+                        &data,
+                    )
+                });
+                root.into_iter().chain(folders).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use crate::common::call;
+use axum::http::{Method, StatusCode};
+use indoc::indoc;
+use mochi::setup_app;
+
+#[tokio::test]
+async fn admin_api() {
+    let app = setup_app("./tests/admin_api".to_string()).unwrap();
+    let apis = "/_admin/systems/system/apis";
+    let yaml = [("Content-Type", "application/yaml")];
+    let json = [("Content-Type", "application/json")];
+
+    let dynamic_api = indoc! {r#"
+        rules:
+          - matches: GET /dynamic
+            response: !OkText "created at runtime"
+    "#};
+    let (status, _, _) = call(
+        &app,
+        Method::PUT,
+        &format!("{apis}/runtime"),
+        &yaml,
+        dynamic_api,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _, body) = call(&app, Method::GET, "/static/system/dynamic", &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "created at runtime")
+    );
+    let (status, _, body) = call(&app, Method::GET, "/static/system/static", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "from file"));
+    let (status, _, body) = call(&app, Method::GET, apis, &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "[\"api\",\"runtime\"]")
+    );
+
+    // Rules can be sent as json
+    let json_rule = r#"{
+        "matches": "GET /json",
+        "query": {"page": {"Regex": "^[0-9]+$"}},
+        "response": {"OkText": "json rule"}
+    }"#;
+    let (status, _, _) = call(
+        &app,
+        Method::POST,
+        &format!("{apis}/runtime/rules"),
+        &json,
+        json_rule,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _, body) = call(&app, Method::GET, "/static/system/json?page=2", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "json rule"));
+
+    let replaced_rule = indoc! {r#"
+        matches: GET /dynamic
+        response: !OkText "replaced"
+    "#};
+    let (status, _, _) = call(
+        &app,
+        Method::PUT,
+        &format!("{apis}/runtime/rules/0"),
+        &yaml,
+        replaced_rule,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, body) = call(&app, Method::GET, "/static/system/dynamic", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "replaced"));
+
+    // Invalid configurations are rejected and the previous one is kept
+    let invalid_rule = indoc! {r#"
+        matches: GET /invalid
+        query:
+          page: !Regex "("
+        response: !OkText "invalid"
+    "#};
+    let (status, _, _) = call(
+        &app,
+        Method::POST,
+        &format!("{apis}/runtime/rules"),
+        &yaml,
+        invalid_rule,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _, body) = call(&app, Method::GET, "/static/system/dynamic", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "replaced"));
+
+    // Apis the router can't serve are rejected too, without breaking the later changes
+    let unsupported_method = indoc! {r#"
+        rules:
+          - matches: PURGE /cache
+            response: !OkText "purged"
+    "#};
+    let conflicting_routes = indoc! {r#"
+        rules:
+          - matches: GET /:id
+            response: !OkText "id"
+          - matches: GET /:name
+            response: !OkText "name"
+    "#};
+    for api in [unsupported_method, conflicting_routes] {
+        let (status, _, _) = call(&app, Method::PUT, &format!("{apis}/other"), &yaml, api).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{api}");
+    }
+    let (status, _, body) = call(&app, Method::GET, "/static/system/dynamic", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "replaced"));
+
+    let (status, _, _) = call(
+        &app,
+        Method::DELETE,
+        &format!("{apis}/runtime/rules/0"),
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, _) = call(&app, Method::GET, "/static/system/dynamic", &[], "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = call(
+        &app,
+        Method::DELETE,
+        &format!("{apis}/runtime/rules/5"),
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // File apis can be managed like runtime apis
+    let (status, _, _) = call(&app, Method::DELETE, &format!("{apis}/api"), &[], "").await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, _) = call(&app, Method::GET, "/static/system/static", &[], "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Apis of api sets are managed with the api_set query parameter, unknown api sets are created
+    let (status, _, _) = call(
+        &app,
+        Method::PUT,
+        &format!("{apis}/runtime?api_set=users"),
+        &yaml,
+        dynamic_api,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _, body) = call(&app, Method::GET, "/static/system/users/dynamic", &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "created at runtime")
+    );
+    let (status, _, _) = call(
+        &app,
+        Method::PUT,
+        &format!("{apis}/runtime/rules/0?api_set=users"),
+        &yaml,
+        replaced_rule,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, body) = call(&app, Method::GET, "/static/system/users/dynamic", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "replaced"));
+    let (status, _, body) =
+        call(&app, Method::GET, &format!("{apis}?api_set=users"), &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "[\"runtime\"]"));
+    let (status, _, _) = call(
+        &app,
+        Method::DELETE,
+        &format!("{apis}/runtime?api_set=orders"),
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = call(
+        &app,
+        Method::DELETE,
+        &format!("{apis}/runtime?api_set=users"),
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, _) = call(&app, Method::GET, "/static/system/users/dynamic", &[], "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Apis of unknown systems create the system
+    let (status, _, _) = call(
+        &app,
+        Method::PUT,
+        "/_admin/systems/other/apis/runtime",
+        &yaml,
+        dynamic_api,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _, body) = call(&app, Method::GET, "/static/other/dynamic", &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "created at runtime")
+    );
+}
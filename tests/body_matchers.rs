@@ -0,0 +1,139 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::StatusCode;
+
+use crate::common::{setup_service, string_body};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn json_body_matchers() {
+    let app = setup_service("./tests/body_matchers");
+
+    let response = app()
+        .oneshot(
+            Request::post("/static/system/rpc")
+                .body(Body::from(
+                    r#"{"method": "getUser", "params": {"user": {"id": 3}}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, r#"{"result": "user"}"#);
+
+    let response = app()
+        .oneshot(
+            Request::post("/static/system/rpc")
+                .body(Body::from(
+                    r#"{"method": "listItems", "params": {"tags": ["old", "new"], "page": 1}}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, r#"{"result": "items"}"#);
+
+    let response = app()
+        .oneshot(
+            Request::post("/static/system/rpc")
+                .body(Body::from(r#"{ "method" : "ping" }"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, r#"{"result": "pong"}"#);
+
+    let response = app()
+        .oneshot(
+            Request::post("/static/system/rpc")
+                .body(Body::from(r#"{"method": "ping", "id": 1}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn xml_body_matchers() {
+    let app = setup_service("./tests/body_matchers");
+
+    let response = app()
+        .oneshot(
+            Request::post("/static/system/soap")
+                .body(Body::from(
+                    r#"<Envelope><Body><GetUser><id>3</id></GetUser></Body></Envelope>"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "<user>3</user>");
+
+    let response = app()
+        .oneshot(
+            Request::post("/static/system/soap")
+                .body(Body::from(
+                    r#"<Envelope><Body><ListUsers /></Body></Envelope>"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "<users/>");
+
+    let response = app()
+        .oneshot(
+            Request::post("/static/system/soap")
+                .body(Body::from(
+                    r#"<Envelope><Body><GetUser><id>4</id></GetUser></Body></Envelope>"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Prefixed names are resolved with the namespaces of the matcher, whatever the document prefixes
+    let envelope = |namespace: &str| {
+        format!(
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"><s:Body><DeleteUser xmlns="{namespace}"><id>3</id></DeleteUser></s:Body></s:Envelope>"#
+        )
+    };
+    let response = app()
+        .oneshot(
+            Request::post("/static/system/soap")
+                .body(Body::from(envelope("http://example.com/users")))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "<deleted>3</deleted>");
+
+    let response = app()
+        .oneshot(
+            Request::post("/static/system/soap")
+                .body(Body::from(envelope("http://example.com/orders")))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
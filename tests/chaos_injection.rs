@@ -0,0 +1,54 @@
+mod common;
+
+use crate::common::call;
+use axum::http::{Method, StatusCode};
+use axum::Router;
+use mochi::setup_app;
+use std::collections::HashMap;
+
+async fn statuses(app: &Router, route: &str, count: usize) -> HashMap<StatusCode, usize> {
+    let mut statuses = HashMap::new();
+    for _ in 0..count {
+        let (status, _, body) = call(app, Method::GET, route, &[], "").await;
+        if status == StatusCode::OK {
+            assert_eq!(body, "ok");
+        }
+        *statuses.entry(status).or_default() += 1;
+    }
+    statuses
+}
+
+#[tokio::test]
+async fn chaos_injection() {
+    let app = setup_app("./tests/chaos_injection".to_string()).unwrap();
+
+    let flaky = statuses(&app, "/static/system/flaky", 200).await;
+    assert_eq!(flaky.values().sum::<usize>(), 200);
+    for (status, expected) in [
+        (StatusCode::OK, 100),
+        (StatusCode::SERVICE_UNAVAILABLE, 60),
+        (StatusCode::GATEWAY_TIMEOUT, 40),
+    ] {
+        let count = flaky[&status];
+        assert!(count.abs_diff(expected) < 30, "{status}: {count}");
+    }
+
+    let down = statuses(&app, "/static/system/down", 10).await;
+    assert_eq!(
+        down,
+        HashMap::from([(StatusCode::INTERNAL_SERVER_ERROR, 10)])
+    );
+}
+
+#[tokio::test]
+async fn seeded_chaos_is_reproducible() {
+    let first = setup_app("./tests/chaos_injection".to_string()).unwrap();
+    let second = setup_app("./tests/chaos_injection".to_string()).unwrap();
+
+    for _ in 0..20 {
+        let (first_status, _, _) = call(&first, Method::GET, "/static/system/flaky", &[], "").await;
+        let (second_status, _, _) =
+            call(&second, Method::GET, "/static/system/flaky", &[], "").await;
+        assert_eq!(first_status, second_status);
+    }
+}
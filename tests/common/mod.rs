@@ -1,8 +1,17 @@
+// Helpers shared by the test crates, each of them only uses some
+#![allow(dead_code)]
+
 use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{HeaderMap, Method, StatusCode};
 use axum::response::Response;
 use axum::routing::RouterIntoService;
+use axum::Router;
 use http_body_util::BodyExt;
 use mochi::setup_app;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tower::ServiceExt;
 
 pub async fn string_body(response: Response) -> String {
     let bytes = response.into_body().collect().await.unwrap();
@@ -13,3 +22,33 @@ pub async fn string_body(response: Response) -> String {
 pub fn setup_service(path: &'static str) -> Box<dyn Fn() -> RouterIntoService<Body>> {
     Box::new(move || setup_app(path.to_string()).unwrap().into_service())
 }
+
+// Sends a request to the app and collects its response
+pub async fn call(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> (StatusCode, HeaderMap, String) {
+    let mut request = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_owned())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    (status, headers, string_body(response).await)
+}
+
+// Serves the router as the upstream of a proxy, on the returned url
+pub async fn upstream(app: Router) -> (String, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{address}/"), server)
+}
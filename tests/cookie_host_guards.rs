@@ -0,0 +1,110 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::StatusCode;
+
+use crate::common::{setup_service, string_body};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn cookie_guards() {
+    let app = setup_service("./tests/cookie_host_guards");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/feature")
+                .header("Cookie", "session=a1b2c3; beta=true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "beta feature");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/feature")
+                .header("Cookie", "session=a1b2c3; beta=false")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn host_guards() {
+    let app = setup_service("./tests/cookie_host_guards");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/tenant")
+                .header("Host", "customer-a.example.com:3000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "customer a");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/tenant")
+                .header("Host", "customer-b.example.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn virtual_hosts() {
+    let app = setup_service("./tests/cookie_host_guards");
+
+    let response = app()
+        .oneshot(
+            Request::get("/route/42")
+                .header("Host", "Tenant.mochi.local:3000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "tenant 42");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/tenant/route/42")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "tenant 42");
+
+    let response = app()
+        .oneshot(
+            Request::get("/route/42")
+                .header("Host", "other.mochi.local")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,67 @@
+use mochi::{serve, setup_app};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+
+async fn spawn_server(path: &str) -> SocketAddr {
+    let tcp_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp_listener.local_addr().unwrap();
+    tokio::spawn(serve(tcp_listener, setup_app(path.to_string()).unwrap()));
+    addr
+}
+
+// Sends a raw http request and reads the connection until it is closed
+async fn raw_get(addr: SocketAddr, path: &str) -> Option<String> {
+    let mut tcp_stream = TcpStream::connect(addr).await.unwrap();
+    tcp_stream
+        .write_all(
+            format!("GET {path} HTTP/1.1\r\nHost: mochi\r\nConnection: close\r\n\r\n").as_bytes(),
+        )
+        .await
+        .unwrap();
+
+    let mut received = vec![];
+    timeout(Duration::from_millis(500), async {
+        let mut buf = [0u8; 1024];
+        loop {
+            match tcp_stream.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+            }
+        }
+    })
+    .await
+    .ok()
+    .map(|_| String::from_utf8_lossy(&received).to_string())
+}
+
+#[tokio::test]
+async fn fault_injection() {
+    let addr = spawn_server("./tests/fault_injection").await;
+
+    let ok = raw_get(addr, "/static/system/ok").await.unwrap();
+    assert!(ok.starts_with("HTTP/1.1 200 OK"));
+    assert!(ok.ends_with("\r\n\r\nok"));
+
+    let close = raw_get(addr, "/static/system/close").await.unwrap();
+    assert_eq!(close, "", "{close:?}");
+
+    let reset = raw_get(addr, "/static/system/reset").await.unwrap();
+    assert!(reset.contains("content-length: 10\r\n"), "{reset:?}");
+    assert!(reset.ends_with("\r\n\r\n01234"));
+
+    let malformed = raw_get(addr, "/static/system/malformed").await.unwrap();
+    assert!(malformed.starts_with("HTTP/1.1 2OO NOT OK\r\n"));
+
+    let garbage = raw_get(addr, "/static/system/garbage").await.unwrap();
+    assert!(!garbage.starts_with("HTTP/1.1"));
+
+    let truncated = raw_get(addr, "/static/system/truncated").await.unwrap();
+    assert!(truncated.contains("content-length: 21\r\n"));
+    assert!(truncated.ends_with("\r\n\r\n0123456789"));
+
+    let hang = raw_get(addr, "/static/system/hang").await;
+    assert_eq!(hang, None);
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{HeaderValue, StatusCode};
+
+use crate::common::{setup_service, string_body};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn header_matchers() {
+    let app = setup_service("./tests/header_matchers");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/regex")
+                .header("Authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "regex");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/regex")
+                .header("Authorization", "Bearer ")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/prefix")
+                .header("Authorization", "Basic dXNlcg==")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "prefix");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/presence")
+                .header("X-Tenant", "any")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "presence");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/presence")
+                .header("X-Tenant", "any")
+                .header("X-Debug", "true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/not")
+                .header("USER", "user")
+                .header("X-Role", "member")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "not");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/not")
+                .header("USER", "user")
+                .header("X-Role", "guest-1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn non_utf8_header_does_not_match() {
+    let app = setup_service("./tests/header_matchers");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/regex")
+                .header(
+                    "Authorization",
+                    HeaderValue::from_bytes(b"Bearer \xff\xfe").unwrap(),
+                )
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use crate::common::call;
+use axum::http::Method;
+use axum::Router;
+use indoc::indoc;
+use mochi::setup_watched_app;
+use std::fs;
+use std::time::Duration;
+use tokio::time::sleep;
+
+// Polls until the configuration is reloaded with the expected response
+async fn wait_for(app: &Router, uri: &str, expected: &str) {
+    for _ in 0..50 {
+        let (_, _, body) = call(app, Method::GET, uri, &[], "").await;
+        if body == expected {
+            return;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Configuration was not reloaded with response '{expected}' on {uri}");
+}
+
+fn api(content: &str) -> String {
+    format!("rules:\n  - matches: GET /route\n    response: !OkText \"{content}\"\n")
+}
+
+#[tokio::test]
+async fn hot_reload() {
+    let conf = tempfile::tempdir().unwrap();
+    let system = conf.path().join("system");
+    fs::create_dir(&system).unwrap();
+    fs::write(system.join("api.yml"), api("initial")).unwrap();
+
+    let (app, _watcher) = setup_watched_app(conf.path().display().to_string(), None).unwrap();
+    let (_, _, body) = call(&app, Method::GET, "/static/system/route", &[], "").await;
+    assert_eq!(body, "initial");
+
+    // Changed files are reloaded
+    fs::write(system.join("api.yml"), api("updated")).unwrap();
+    wait_for(&app, "/static/system/route", "updated").await;
+
+    // Files that can't be decoded keep the previous configuration
+    fs::write(system.join("api.yml"), "rules: [").unwrap();
+    sleep(Duration::from_secs(1)).await;
+    let (_, _, body) = call(&app, Method::GET, "/static/system/route", &[], "").await;
+    assert_eq!(body, "updated");
+
+    // Configurations failing shape validation keep the previous configuration
+    fs::write(system.join("api.yml"), api("reshaped")).unwrap();
+    fs::write(
+        system.join("shape.yml"),
+        indoc! {"
+            shape:
+              - GET /route
+              - GET /other
+        "},
+    )
+    .unwrap();
+    sleep(Duration::from_secs(1)).await;
+    let (_, _, body) = call(&app, Method::GET, "/static/system/route", &[], "").await;
+    assert_eq!(body, "updated");
+
+    // New systems are served once valid again
+    fs::remove_file(system.join("shape.yml")).unwrap();
+    let other = conf.path().join("other");
+    fs::create_dir(&other).unwrap();
+    fs::write(other.join("api.yml"), api("other")).unwrap();
+    wait_for(&app, "/static/other/route", "other").await;
+    let (_, _, body) = call(&app, Method::GET, "/static/system/route", &[], "").await;
+    assert_eq!(body, "reshaped");
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::StatusCode;
+use std::time::{Duration, Instant};
+
+use crate::common::{setup_service, string_body};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn latency_profiles() {
+    let app = setup_service("./tests/latency_profiles");
+
+    for route in ["uniform", "percentiles", "normal"] {
+        let start = Instant::now();
+
+        let response = app()
+            .oneshot(
+                Request::get(format!("/static/system/{route}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(string_body(response).await, route);
+    }
+}
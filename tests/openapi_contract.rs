@@ -0,0 +1,98 @@
+mod common;
+
+use crate::common::call;
+use axum::http::{Method, StatusCode};
+use mochi::{setup_app, validate_conf};
+
+const JSON: [(&str, &str); 1] = [("Content-Type", "application/json")];
+
+#[tokio::test]
+async fn openapi_contract_requests() {
+    let app = setup_app("./tests/openapi_contract/valid".to_string()).unwrap();
+
+    let (status, _, body) = call(
+        &app,
+        Method::POST,
+        "/static/shop/orders",
+        &JSON,
+        r#"{"item": "book", "quantity": 1}"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body, "{\"item\": \"book\", \"quantity\": 1}\n");
+
+    // Request bodies not following the schema of the operation are rejected with the errors
+    let (status, _, body) = call(
+        &app,
+        Method::POST,
+        "/static/shop/orders",
+        &JSON,
+        r#"{"quantity": 0}"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body,
+        "Request body doesn't follow the schema of the openapi document:\n\
+         - \"item\" is a required property\n\
+         - 0 is less than the minimum of 1 at '/quantity'"
+    );
+
+    // Nullable fields accept null
+    let (status, _, _) = call(
+        &app,
+        Method::POST,
+        "/static/shop/orders",
+        &JSON,
+        r#"{"item": "book", "quantity": 1, "note": null}"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, _, body) =
+        call(&app, Method::POST, "/static/shop/orders", &JSON, "not json").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("Request body is not valid json"));
+
+    // Optional bodies can be left out, required ones can't, and bodies that aren't json are not
+    // validated
+    let (status, _, _) = call(&app, Method::POST, "/static/shop/orders", &[], "").await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _, body) = call(&app, Method::PUT, "/static/shop/orders/1", &[], "").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("Request body is required"));
+    let text = [("Content-Type", "text/plain")];
+    let (status, _, _) = call(&app, Method::POST, "/static/shop/orders", &text, "book").await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // Operations without request schema are not validated
+    let (status, _, _) = call(
+        &app,
+        Method::GET,
+        "/static/shop/orders/1",
+        &JSON,
+        "not json",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[test]
+fn openapi_contract_responses() {
+    // Responses are validated against the schema of their status, or of their status range
+    let errors = validate_conf("./tests/openapi_contract/invalid".to_string());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        format!("{:#}", errors[0]),
+        "Validating api file './tests/openapi_contract/invalid/shop/api.yml' against the shape of api set 'shop': \
+         Api name: shop\n -> Responses don't follow the openapi document:\n \
+         - Response 201 of rule 'POST -> /orders' doesn't follow its schema: 0 is less than the minimum of 1 at '/quantity'\n \
+         - Response 404 of rule 'GET -> /orders/:id' doesn't follow its schema: \"error\" is a required property"
+    );
+
+    // Nullable fields of responses can be null
+    assert!(validate_conf("./tests/openapi_contract/valid".to_string()).is_empty());
+
+    // Invalid configurations are not served
+    assert!(setup_app("./tests/openapi_contract/invalid".to_string()).is_err());
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use crate::common::call;
+use axum::http::{Method, StatusCode};
+use mochi::setup_app;
+use serde_json::{json, Value};
+
+fn json_body(body: &str) -> Value {
+    serde_json::from_str(body).unwrap()
+}
+
+#[tokio::test]
+async fn openapi_import() {
+    let app = setup_app("./tests/openapi_import".to_string()).unwrap();
+
+    // Examples of the media type
+    let (status, headers, body) = call(&app, Method::GET, "/static/petstore/pets", &[], "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["Content-Type"], "application/json");
+    assert_eq!(json_body(&body), json!([{"id": 1, "name": "Rex"}]));
+
+    // Examples generated from the response schema
+    let (status, _, body) = call(&app, Method::POST, "/static/petstore/pets", &[], "").await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        json_body(&body),
+        json!({"id": 0, "name": "Felix", "tags": ["string"], "status": "available"})
+    );
+
+    // Referenced responses and examples, the successful response is served
+    let (status, _, body) = call(&app, Method::GET, "/static/petstore/pets/7", &[], "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json_body(&body), json!({"id": 1, "name": "Rex"}));
+
+    // Data files take precedence over the imported examples
+    let (status, headers, body) =
+        call(&app, Method::DELETE, "/static/petstore/pets/7", &[], "").await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(headers["Content-Type"], "text/plain");
+    assert_eq!(body, "deletion scheduled");
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use crate::common::{call, upstream};
+use axum::http::{Method, StatusCode};
+use axum::response::Redirect;
+use axum::routing::get;
+use axum::Router;
+use mochi::{setup_app, validate_conf};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::sleep;
+
+fn write_proxy(conf: &Path, api: &str, content: String) {
+    let folder = conf.join("system").join(api);
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(folder.join("proxy.yml"), content).unwrap();
+}
+
+#[tokio::test]
+async fn proxy_client() {
+    let (url, _) = upstream(
+        Router::new()
+            .route("/old", get(|| async { Redirect::temporary("/new") }))
+            .route("/new", get(|| async { "moved" }))
+            .route(
+                "/slow",
+                get(|| async {
+                    sleep(Duration::from_secs(5)).await;
+                    "slow"
+                }),
+            ),
+    )
+    .await;
+    let conf = tempfile::tempdir().unwrap();
+    write_proxy(conf.path(), "default", format!("url: {url}\n"));
+    write_proxy(
+        conf.path(),
+        "configured",
+        format!("url: {url}\nread_timeout: 200\nconnect_timeout: 200\nmax_redirects: 0\npool_max_idle_per_host: 2\n"),
+    );
+    let app = setup_app(conf.path().display().to_string()).unwrap();
+
+    // Redirects are followed by default, or sent back to the client
+    let (status, _, body) = call(&app, Method::GET, "/proxy/system/default/old", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "moved"));
+    let (status, _, _) = call(&app, Method::GET, "/proxy/system/configured/old", &[], "").await;
+    assert_eq!(status, StatusCode::TEMPORARY_REDIRECT);
+
+    // Upstreams slower than the read timeout fail the request
+    let (status, _, body) = call(&app, Method::GET, "/proxy/system/configured/slow", &[], "").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body.contains("Sending request"), "{body}");
+
+    // Missing pem files are reported by the validation
+    write_proxy(
+        conf.path(),
+        "secure",
+        format!("url: {url}\nca_bundle: ca.pem\ninsecure: true\n"),
+    );
+    let errors = validate_conf(conf.path().display().to_string());
+    assert_eq!(errors.len(), 1);
+    assert!(format!("{:#}", errors[0]).contains("Reading pem file"));
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use crate::common::{call, upstream};
+use axum::http::{Method, StatusCode};
+use axum::routing::{get, post};
+use axum::Router;
+use mochi::setup_app;
+use std::fs;
+
+#[tokio::test]
+async fn proxy_fallback() {
+    let (url, _) = upstream(
+        Router::new()
+            .route(
+                "/users/:id",
+                get(|| async { "real user" })
+                    .post(|| async { (StatusCode::CREATED, "created user") }),
+            )
+            .route("/orders", get(|| async { "real orders" }))
+            .route("/items/:id", get(|| async { "real item" }))
+            .route("/", get(|| async { "real root" }))
+            .route("/echo", post(|body: String| async move { body }))
+            .route("/search", post(|body: String| async move { body })),
+    )
+    .await;
+    let conf = tempfile::tempdir().unwrap();
+    let folder = conf.path().join("system").join("shop");
+    fs::create_dir_all(&folder).unwrap();
+    fs::copy(
+        "tests/proxy_fallback/system/shop/api.yml",
+        folder.join("api.yml"),
+    )
+    .unwrap();
+    fs::write(
+        folder.join("proxy.yml"),
+        format!("url: {url}\nfallback: true\n"),
+    )
+    .unwrap();
+    let app = setup_app(conf.path().display().to_string()).unwrap();
+
+    // Matching rules answer
+    let (status, _, body) = call(&app, Method::GET, "/static/system/shop/users/1", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "mocked user"));
+    let (status, _, body) = call(&app, Method::GET, "/static/system/shop/items/7", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "mocked item"));
+    let (status, _, body) = call(
+        &app,
+        Method::GET,
+        "/static/system/shop/orders?status=open",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "open orders"));
+    let (status, _, body) = call(&app, Method::GET, "/static/system/shop/missing", &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::NOT_FOUND, "mocked missing")
+    );
+
+    // Routes, methods and requests without matching rule are forwarded
+    let (status, _, body) = call(&app, Method::GET, "/static/system/shop/users/42", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "real user"));
+    let (status, _, body) = call(&app, Method::POST, "/static/system/shop/users/1", &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::CREATED, "created user")
+    );
+    let (status, _, body) = call(
+        &app,
+        Method::GET,
+        "/static/system/shop/orders?status=closed",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "real orders"));
+
+    // Request bodies reach the dependency, whether the rules read them or not
+    let (status, _, body) = call(
+        &app,
+        Method::POST,
+        "/static/system/shop/echo",
+        &[],
+        "streamed",
+    )
+    .await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "streamed"));
+    let (_, _, body) = call(
+        &app,
+        Method::POST,
+        "/static/system/shop/search",
+        &[],
+        r#"{"mocked": true}"#,
+    )
+    .await;
+    assert_eq!(body, "mocked search");
+    let (_, _, body) = call(
+        &app,
+        Method::POST,
+        "/static/system/shop/search",
+        &[],
+        r#"{"mocked": false}"#,
+    )
+    .await;
+    assert_eq!(body, r#"{"mocked": false}"#);
+
+    // Parameter routes of the api set don't keep the other routes from being forwarded
+    let (status, _, body) = call(&app, Method::GET, "/static/system/shop/7", &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "mocked top level")
+    );
+
+    // The root of the api set is forwarded too
+    for uri in ["/static/system/shop", "/static/system/shop/"] {
+        let (status, _, body) = call(&app, Method::GET, uri, &[], "").await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "real root"));
+    }
+}
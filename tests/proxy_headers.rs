@@ -0,0 +1,83 @@
+mod common;
+
+use crate::common::upstream;
+use axum::body::Body;
+use axum::extract::{ConnectInfo, Request};
+use axum::http::HeaderMap;
+use axum::routing::get;
+use axum::{Json, Router};
+use http_body_util::BodyExt;
+use mochi::setup_app;
+use serde_json::{Map, Value};
+use std::fs;
+use std::net::SocketAddr;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn proxy_headers() {
+    // Dependency answering the headers it received
+    let (url, _) = upstream(Router::new().route(
+        "/echo",
+        get(|headers: HeaderMap| async move {
+            let received: Map<String, Value> = headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_str().unwrap().into()))
+                .collect();
+            (
+                [
+                    ("x-correlation-id", "abc-123"),
+                    ("x-internal", "secret"),
+                    ("connection", "close"),
+                ],
+                Json(received),
+            )
+        }),
+    ))
+    .await;
+    let conf = tempfile::tempdir().unwrap();
+    let folder = conf.path().join("system").join("api");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(
+        folder.join("proxy.yml"),
+        fs::read_to_string("tests/proxy_headers/proxy.yml")
+            .unwrap()
+            .replace("{url}", &url),
+    )
+    .unwrap();
+    let app = setup_app(conf.path().display().to_string()).unwrap();
+
+    let mut request = Request::get("/proxy/system/api/echo")
+        .header("Host", "mochi.local")
+        .header("Authorization", "Bearer token")
+        .header("X-Correlation-Id", "abc-123")
+        .header("X-Env", "staging")
+        .header("Cookie", "session=1")
+        .header("Connection", "x-connection-only")
+        .header("X-Connection-Only", "1")
+        .header("X-Forwarded-For", "10.0.0.1")
+        .body(Body::empty())
+        .unwrap();
+    request.extensions_mut().insert(ConnectInfo(
+        "192.168.1.2:4000".parse::<SocketAddr>().unwrap(),
+    ));
+    let response = app.oneshot(request).await.unwrap();
+
+    let headers = response.headers().clone();
+    assert_eq!(headers["x-correlation-id"], "abc-123");
+    assert_eq!(headers["x-served-by"], "mochi");
+    assert!(!headers.contains_key("x-internal"));
+    assert!(!headers.contains_key("connection"));
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let received: Map<String, Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(received["authorization"], "Bearer token");
+    assert_eq!(received["x-correlation-id"], "abc-123");
+    assert_eq!(received["x-env"], "production");
+    assert_eq!(received["x-api-key"], "secret");
+    assert_eq!(received["x-forwarded-for"], "10.0.0.1, 192.168.1.2");
+    assert_eq!(received["x-forwarded-proto"], "http");
+    assert_eq!(received["x-forwarded-host"], "mochi.local");
+    assert_ne!(received["host"], "mochi.local");
+    assert!(!received.contains_key("cookie"));
+    assert!(!received.contains_key("x-connection-only"));
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use crate::common::{call, upstream};
+use axum::http::{Method, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use mochi::setup_app;
+use serde_json::json;
+use std::fs;
+
+#[tokio::test]
+async fn proxy_recording() {
+    // Dependency whose responses are recorded, stopped before replaying them
+    let (url, server) = upstream(
+        Router::new()
+            .route(
+                "/users/42",
+                get(|| async { Json(json!({ "id": 42, "name": "Ada" })) }),
+            )
+            .route(
+                "/users/42/page/1",
+                get(|| async { Json(json!({ "page": 1 })) }),
+            )
+            .route(
+                "/users",
+                post(|| async { (StatusCode::CREATED, "created") }),
+            )
+            .route("/hello%20world", get(|| async { "hello" }))
+            .route("/:name", get(|| async { "parameter" })),
+    )
+    .await;
+    let conf = tempfile::tempdir().unwrap();
+    let folder = conf.path().join("system").join("users");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(
+        folder.join("proxy.yml"),
+        format!("url: {url}\nrecord: true\n"),
+    )
+    .unwrap();
+
+    let app = setup_app(conf.path().display().to_string()).unwrap();
+    let (status, headers, user) = call(
+        &app,
+        Method::GET,
+        "/proxy/system/users/users/42?page=1",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["Content-Type"], "application/json");
+    assert_eq!(user, r#"{"id":42,"name":"Ada"}"#);
+    let (status, _, created) = call(&app, Method::POST, "/proxy/system/users/users", &[], "").await;
+    assert_eq!(status, StatusCode::CREATED);
+    // Endpoints made of the same words are recorded apart
+    let (_, _, page) = call(
+        &app,
+        Method::GET,
+        "/proxy/system/users/users/42/page/1",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(page, r#"{"page":1}"#);
+    // Paths are recorded as received, those that can't be routes are not recorded
+    let (_, _, hello) = call(
+        &app,
+        Method::GET,
+        "/proxy/system/users/hello%20world",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(hello, "hello");
+    let (_, _, parameter) = call(&app, Method::GET, "/proxy/system/users/:me", &[], "").await;
+    assert_eq!(parameter, "parameter");
+
+    // Recording the same endpoint again replaces the previous rule
+    call(
+        &app,
+        Method::GET,
+        "/proxy/system/users/users/42?page=1",
+        &[],
+        "",
+    )
+    .await;
+    let recorded = fs::read_to_string(folder.join("api-recorded.yml")).unwrap();
+    assert_eq!(recorded.matches("matches:").count(), 4);
+    assert!(!recorded.contains(":me"));
+    let data = fs::read_dir(folder.join("data/recorded"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("get-users-42-page-1-"))
+        .count();
+    assert_eq!(data, 2);
+
+    // Recorded responses are replayed without the dependency
+    server.abort();
+    let app = setup_app(conf.path().display().to_string()).unwrap();
+    let (status, headers, body) = call(
+        &app,
+        Method::GET,
+        "/static/system/users/users/42?page=1",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["Content-Type"], "application/json");
+    assert_eq!(body, user);
+    let (status, _, body) = call(&app, Method::POST, "/static/system/users/users", &[], "").await;
+    assert_eq!((status, body), (StatusCode::CREATED, created));
+    let (_, _, body) = call(
+        &app,
+        Method::GET,
+        "/static/system/users/users/42/page/1",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(body, page);
+    let (_, _, body) = call(
+        &app,
+        Method::GET,
+        "/static/system/users/hello%20world",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(body, hello);
+}
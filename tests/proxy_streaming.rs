@@ -0,0 +1,138 @@
+mod common;
+
+use crate::common::{call, string_body, upstream};
+use axum::body::{Body, Bytes};
+use axum::extract::Request;
+use axum::http::Method;
+use axum::routing::{get, post};
+use axum::Router;
+use futures_util::stream;
+use http_body_util::BodyExt;
+use mochi::setup_app;
+use serde_json::Value;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::timeout;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn proxy_streaming() {
+    // Dependency echoing binary uploads, telling when it starts receiving an upload, and sending
+    // events until told to stop
+    let stop = Arc::new(Notify::new());
+    let events_stop = stop.clone();
+    let received = Arc::new(Notify::new());
+    let chunks_received = received.clone();
+    let (url, _) = upstream(
+        Router::new()
+            .route("/upload", post(|body: Bytes| async move { body }))
+            .route(
+                "/chunks",
+                post(|request: Request| async move {
+                    let mut body = request.into_body();
+                    let first = body.frame().await.unwrap().unwrap().into_data().unwrap();
+                    chunks_received.notify_one();
+                    let rest = body.collect().await.unwrap().to_bytes();
+                    [first, rest].concat()
+                }),
+            )
+            .route(
+                "/events",
+                get(|| async move {
+                    let events = stream::unfold(0, move |sent| {
+                        let stop = events_stop.clone();
+                        async move {
+                            match sent {
+                                0 => Some((Ok::<_, std::io::Error>("data: first\n\n"), 1)),
+                                1 => {
+                                    stop.notified().await;
+                                    Some((Ok("data: last\n\n"), 2))
+                                }
+                                _ => None,
+                            }
+                        }
+                    });
+                    (
+                        [("Content-Type", "text/event-stream")],
+                        Body::from_stream(events),
+                    )
+                }),
+            ),
+    )
+    .await;
+    let conf = tempfile::tempdir().unwrap();
+    let folder = conf.path().join("system").join("api");
+    fs::create_dir_all(&folder).unwrap();
+    fs::write(folder.join("proxy.yml"), format!("url: {url}\n")).unwrap();
+    let app = setup_app(conf.path().display().to_string()).unwrap();
+
+    // Binary payloads larger than the journal keeps pass through unchanged
+    let payload: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/proxy/system/api/upload")
+                .header("Content-Type", "application/octet-stream")
+                .body(Body::from(payload.clone()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body.to_vec(), payload);
+
+    // Uploads are forwarded as they are sent, the journal keeps them once forwarded
+    let sent = received.clone();
+    let chunks = stream::unfold(0, move |sent_chunks| {
+        let received = sent.clone();
+        async move {
+            match sent_chunks {
+                0 => Some((Ok::<_, std::io::Error>("first chunk, "), 1)),
+                1 => {
+                    received.notified().await;
+                    Some((Ok("last chunk"), 2))
+                }
+                _ => None,
+            }
+        }
+    });
+    let response = timeout(
+        Duration::from_secs(5),
+        app.clone().oneshot(
+            Request::post("/proxy/system/api/chunks")
+                .body(Body::from_stream(chunks))
+                .unwrap(),
+        ),
+    )
+    .await
+    .expect("upload should be forwarded before it ends")
+    .unwrap();
+    assert_eq!(string_body(response).await, "first chunk, last chunk");
+    let (_, _, journal) = call(&app, Method::GET, "/_admin/requests?path=chunks", &[], "").await;
+    let journal: Value = serde_json::from_str(&journal).unwrap();
+    assert_eq!(journal[0]["body"], "first chunk, last chunk");
+
+    // Events are received as the upstream sends them
+    let response = app
+        .oneshot(
+            Request::get("/proxy/system/api/events")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    let mut body = response.into_body();
+    let first = timeout(Duration::from_secs(5), body.frame())
+        .await
+        .expect("first event should be streamed before the upstream response ends")
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.into_data().unwrap(), "data: first\n\n");
+
+    stop.notify_one();
+    let rest = body.collect().await.unwrap().to_bytes();
+    assert_eq!(rest, "data: last\n\n");
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::StatusCode;
+
+use crate::common::{setup_service, string_body};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn query_matchers() {
+    let app = setup_service("./tests/query_matchers");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/search?type=book")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "book");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/search?page=2&type=movie")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "movie");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/search?type=tv&season=1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "series");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/search")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "all");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/search?type=tv")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
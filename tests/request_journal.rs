@@ -0,0 +1,127 @@
+mod common;
+
+use crate::common::call;
+use axum::http::{Method, StatusCode};
+use axum::Router;
+use indoc::indoc;
+use mochi::setup_app;
+use serde_json::Value;
+
+const CLIENT: [(&str, &str); 1] = [("x-client", "contract-test")];
+
+async fn journal(app: &Router, query: &str) -> Vec<Value> {
+    let (status, _, body) = call(
+        app,
+        Method::GET,
+        &format!("/_admin/requests{query}"),
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_str(&body).unwrap()
+}
+
+#[tokio::test]
+async fn request_journal() {
+    let app = setup_app("./tests/request_journal".to_string()).unwrap();
+
+    call(
+        &app,
+        Method::POST,
+        "/static/system/orders",
+        &CLIENT,
+        r#"{"id": 1}"#,
+    )
+    .await;
+    call(
+        &app,
+        Method::POST,
+        "/static/system/orders",
+        &CLIENT,
+        r#"{"id": 2}"#,
+    )
+    .await;
+    call(
+        &app,
+        Method::GET,
+        "/static/system/orders?page=1",
+        &CLIENT,
+        "",
+    )
+    .await;
+    call(&app, Method::GET, "/static/system/unknown", &CLIENT, "").await;
+
+    let entries = journal(&app, "").await;
+    assert_eq!(entries.len(), 4);
+
+    let first = &entries[0];
+    assert_eq!(first["method"], "POST");
+    assert_eq!(first["uri"], "/static/system/orders");
+    assert_eq!(first["headers"]["x-client"], "contract-test");
+    assert_eq!(first["body"], r#"{"id": 1}"#);
+    assert_eq!(first["system"], "system");
+    assert_eq!(first["api"], "api-orders");
+    assert_eq!(first["rule"], "POST -> /orders");
+    assert_eq!(first["status"], 201);
+
+    let unmatched = &entries[3];
+    assert_eq!(unmatched["system"], Value::Null);
+    assert_eq!(unmatched["status"], 404);
+
+    assert_eq!(journal(&app, "?method=get").await.len(), 2);
+    assert_eq!(
+        journal(&app, "?path=^/static/system/orders$").await.len(),
+        3
+    );
+    assert_eq!(journal(&app, "?status=201&api=api-orders").await.len(), 2);
+
+    let verification = indoc! {r#"
+        method: POST
+        path: /static/system/orders
+        body:
+          - !JsonPartial '{"id": 2}'
+        count: 1
+    "#};
+    let (status, _, body) = call(
+        &app,
+        Method::POST,
+        "/_admin/requests/verify",
+        &[],
+        verification,
+    )
+    .await;
+    assert_eq!(
+        (status, body.as_str()),
+        (
+            StatusCode::OK,
+            r#"{"verified":true,"expected":1,"actual":1}"#
+        )
+    );
+
+    let verification = indoc! {r#"
+        path: !Prefix /static/system/orders
+        headers:
+          X-Client: contract-test
+        count: 2
+    "#};
+    let (status, _, body) = call(
+        &app,
+        Method::POST,
+        "/_admin/requests/verify",
+        &[],
+        verification,
+    )
+    .await;
+    assert_eq!(
+        (status, body.as_str()),
+        (
+            StatusCode::EXPECTATION_FAILED,
+            r#"{"verified":false,"expected":2,"actual":3}"#
+        )
+    );
+
+    let (status, _, _) = call(&app, Method::DELETE, "/_admin/requests", &[], "").await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(journal(&app, "").await.is_empty());
+}
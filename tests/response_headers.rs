@@ -0,0 +1,76 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{Method, StatusCode};
+
+use crate::common::{call, setup_service, string_body};
+use mochi::setup_app;
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn response_headers() {
+    let app = setup_service("./tests/response_headers");
+
+    let created = app()
+        .oneshot(
+            Request::post("/static/system/items")
+                .header("x-correlation-id", "abc-123")
+                .body(Body::from("{\"id\": \"42\"}"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(created.status(), StatusCode::CREATED);
+    assert_eq!(created.headers()["Location"], "/items/42");
+    assert_eq!(created.headers()["ETag"], "\"v2\"");
+    assert_eq!(created.headers()["X-Request-Id"], "abc-123");
+    assert_eq!(created.headers()["Content-Type"], "application/json");
+    assert_eq!(string_body(created).await, "{\"id\": \"42\"}\n");
+
+    let moved = app()
+        .oneshot(
+            Request::get("/static/system/old/7")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(moved.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(moved.headers()["Location"], "/new/7");
+    assert_eq!(
+        moved
+            .headers()
+            .get_all("Content-Type")
+            .iter()
+            .collect::<Vec<_>>(),
+        ["text/html"]
+    );
+
+    let limited = app()
+        .oneshot(
+            Request::get("/static/system/limited")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(limited.status(), StatusCode::OK);
+    assert_eq!(limited.headers()["Retry-After"], "120");
+    assert_eq!(limited.headers()["Set-Cookie"], "session=abc; Path=/");
+    assert_eq!(string_body(limited).await, "slow down");
+
+    // Inline responses of a sequence have their own headers, over those of the rule
+    let app = setup_app("./tests/response_headers".to_string()).unwrap();
+    let (status, headers, _) = call(&app, Method::GET, "/static/system/retried", &[], "").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(headers["Retry-After"], "5");
+    assert_eq!(headers["X-Attempt"], "first");
+    let (status, headers, body) = call(&app, Method::GET, "/static/system/retried", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "done"));
+    assert!(!headers.contains_key("Retry-After"));
+    assert_eq!(headers["X-Attempt"], "any");
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use crate::common::call;
+use axum::http::{Method, StatusCode};
+use indoc::indoc;
+use mochi::setup_app;
+
+#[tokio::test]
+async fn response_sequences() {
+    let app = setup_app("./tests/response_sequences".to_string()).unwrap();
+
+    let unavailable = (StatusCode::SERVICE_UNAVAILABLE, "unavailable");
+    let ok = (StatusCode::OK, "ok");
+    for expected in [unavailable, unavailable, ok, ok, ok] {
+        let (status, _, body) = call(&app, Method::GET, "/static/system/retry", &[], "").await;
+        assert_eq!((status, body.as_str()), expected);
+    }
+
+    for expected in ["on", "off", "on", "off"] {
+        let (status, _, body) = call(&app, Method::GET, "/static/system/toggle", &[], "").await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, expected));
+    }
+
+    let (status, _, body) = call(&app, Method::GET, "/static/system/token", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "first token"));
+    for _ in 0..2 {
+        let (status, _, body) = call(&app, Method::GET, "/static/system/token", &[], "").await;
+        assert_eq!(
+            (status, body.as_str()),
+            (StatusCode::GONE, "no more tokens")
+        );
+    }
+}
+
+#[tokio::test]
+async fn sequences_survive_configuration_changes() {
+    let app = setup_app("./tests/response_sequences".to_string()).unwrap();
+
+    for _ in 0..2 {
+        let (status, _, _) = call(&app, Method::GET, "/static/system/retry", &[], "").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    // Changing another api rebuilds the router, the sequence keeps its position
+    let other = indoc! {r#"
+        rules:
+          - matches: GET /other
+            response: !OkText "other"
+    "#};
+    let (status, _, _) = call(
+        &app,
+        Method::PUT,
+        "/_admin/systems/system/apis/other",
+        &[("Content-Type", "application/yaml")],
+        other,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, _, body) = call(&app, Method::GET, "/static/system/retry", &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "ok"));
+
+    // Removing a rule doesn't give its position to the sequences of the next rules
+    let (status, _, _) = call(
+        &app,
+        Method::DELETE,
+        "/_admin/systems/system/apis/api/rules/0",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    for expected in ["on", "off"] {
+        let (status, _, body) = call(&app, Method::GET, "/static/system/toggle", &[], "").await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, expected));
+    }
+}
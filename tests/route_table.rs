@@ -0,0 +1,58 @@
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::StatusCode;
+use http_body_util::BodyExt;
+use indoc::indoc;
+use mochi::{route_table, setup_app};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+#[test]
+fn route_table_text() {
+    assert_eq!(
+        route_table("./tests/route_table".to_string()).unwrap(),
+        indoc! {"
+            METHOD  URL                               HEADERS                      GUARDS                                LATENCY  STATUS    CONTENT TYPE                  SOURCE
+            GET     /static/system/items              Authorization present        -                                     100ms    200       application/json              ./tests/route_table/system/api.yml
+            POST    /static/system/items              Authorization present        -                                     -        201, 409  application/json, text/plain  ./tests/route_table/system/api.yml
+            GET     /static/system/orders/orders/:id  Tenant matching regex '^t-'  cookie session present                -        200       text/plain                    ./tests/route_table/system/orders/api.yml
+            GET     /static/system/search             Authorization present        query page equal to '1'               -        200       text/plain                    ./tests/route_table/system/api.yml
+            GET     /static/system/search             Authorization present        query page matching regex '^[0-9]+$'  -        200       text/plain                    ./tests/route_table/system/api.yml"
+        }
+    );
+}
+
+#[tokio::test]
+async fn route_table_admin() {
+    let app = setup_app("./tests/route_table".to_string()).unwrap();
+
+    let response = app
+        .oneshot(Request::get("/_admin/routes").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let routes: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(routes.as_array().unwrap().len(), 5);
+    assert_eq!(
+        routes[1],
+        json!({
+            "method": "POST",
+            "url": "/static/system/items",
+            "headers": ["Authorization present"],
+            "guards": [],
+            "latency": null,
+            "status": "201, 409",
+            "content_type": "application/json, text/plain",
+            "source": "./tests/route_table/system/api.yml",
+        })
+    );
+
+    // Rules of a route told apart by their query are both listed, with their guards
+    assert_eq!(routes[3]["guards"], json!(["query page equal to '1'"]));
+    assert_eq!(
+        routes[4]["guards"],
+        json!(["query page matching regex '^[0-9]+$'"])
+    );
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::StatusCode;
+use indoc::indoc;
+
+use crate::common::{setup_service, string_body};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn rule_priority() {
+    let app = setup_service("./tests/rule_priority");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/route")
+                .header("X-User", "user")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "guarded");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/route")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "fallback");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/item?id=3")
+                .header("X-User", "user")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "item by id");
+}
+
+#[tokio::test]
+async fn rule_mismatch_diagnostics() {
+    let app = setup_service("./tests/rule_priority");
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/item?id=abc")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        string_body(response).await,
+        indoc!(
+            r###"No rule matched for request [GET] /static/system/item?id=abc
+             - rule 1 'GET -> /item' (priority 5):
+               - expected query parameter 'id' to be matching regex '^[0-9]+$'
+             - rule 2 'GET -> /item' (priority 0):
+               - expected header 'X-User' to be present"###
+        )
+    );
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use crate::common::call;
+use axum::http::{Method, StatusCode};
+use mochi::setup_app;
+
+#[tokio::test]
+async fn scenarios() {
+    let app = setup_app("./tests/scenarios".to_string()).unwrap();
+    let order = "/static/system/order";
+
+    let (status, _, body) = call(&app, Method::GET, order, &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::NOT_FOUND, "no order"));
+    let (status, _, _) = call(&app, Method::POST, order, &[], "").await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _, body) = call(&app, Method::GET, order, &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::OK, "{\"id\": 1}"));
+    let (status, _, body) = call(&app, Method::GET, "/_admin/scenarios/system", &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "{\"order\":\"created\"}")
+    );
+    let (status, _, _) = call(&app, Method::DELETE, order, &[], "").await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, body) = call(&app, Method::GET, order, &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::NOT_FOUND, "deleted order")
+    );
+
+    // A deleted order can't be deleted again
+    let (status, _, body) = call(&app, Method::DELETE, order, &[], "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body.contains("expected scenario 'order' to be in state 'created', found 'deleted'"));
+
+    let (status, _, _) = call(
+        &app,
+        Method::DELETE,
+        "/_admin/scenarios/system/order",
+        &[],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, body) = call(&app, Method::GET, "/_admin/scenarios/system", &[], "").await;
+    assert_eq!(
+        (status, body.as_str()),
+        (StatusCode::OK, "{\"order\":\"Started\"}")
+    );
+    let (status, _, body) = call(&app, Method::GET, order, &[], "").await;
+    assert_eq!((status, body.as_str()), (StatusCode::NOT_FOUND, "no order"));
+
+    let (status, _, _) = call(&app, Method::GET, "/_admin/scenarios/unknown", &[], "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn concurrent_scenario_transitions() {
+    let app = setup_app("./tests/scenario_transitions".to_string()).unwrap();
+
+    // Only one of the concurrent requests matches the initial state
+    let claims = (0..20).map(|_| call(&app, Method::POST, "/static/system/claim", &[], ""));
+    let statuses = futures_util::future::join_all(claims)
+        .await
+        .into_iter()
+        .map(|(status, _, _)| status)
+        .collect::<Vec<_>>();
+    assert_eq!(statuses.iter().filter(|s| **s == StatusCode::OK).count(), 1);
+    assert_eq!(
+        statuses
+            .iter()
+            .filter(|s| **s == StatusCode::CONFLICT)
+            .count(),
+        19
+    );
+
+    // Responses replaced by chaos don't move the scenario
+    let (status, _, _) = call(&app, Method::POST, "/static/system/payment", &[], "").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let (_, _, body) = call(&app, Method::GET, "/_admin/scenarios/system", &[], "").await;
+    assert_eq!(body, "{\"claim\":\"claimed\",\"payment\":\"Started\"}");
+}
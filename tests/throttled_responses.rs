@@ -0,0 +1,52 @@
+mod common;
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::StatusCode;
+use std::time::{Duration, Instant};
+
+use crate::common::{setup_service, string_body};
+use tower::ServiceExt;
+
+#[tokio::test]
+async fn throughput() {
+    let app = setup_service("./tests/throttled_responses");
+
+    let start = Instant::now();
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/throughput")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["Content-Length"], "100");
+    assert_eq!(string_body(response).await, "0123456789".repeat(10));
+    // 100 bytes at 500 bytes per second
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn chunk_delay() {
+    let app = setup_service("./tests/throttled_responses");
+
+    let start = Instant::now();
+
+    let response = app()
+        .oneshot(
+            Request::get("/static/system/chunks")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(string_body(response).await, "0123456789");
+    // 3 chunks, delayed twice
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
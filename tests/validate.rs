@@ -0,0 +1,41 @@
+use itertools::Itertools;
+use mochi::validate_conf;
+
+#[test]
+fn validate() {
+    assert!(validate_conf("./tests/basic".to_string()).is_empty());
+
+    let errors = validate_conf("./tests/validate".to_string())
+        .iter()
+        .map(|e| format!("{e:#}"))
+        .collect_vec();
+
+    // All the errors are reported, with the file and line they come from
+    assert_eq!(errors.len(), 8, "{errors:#?}");
+    assert!(errors[0].starts_with(
+        "Could not decode response data yaml file './tests/validate/system/data/broken-status.yml'"
+    ));
+    assert!(errors[1].starts_with(
+        "Could not decode response data yaml file './tests/validate/system/data/broken-yaml.yml'"
+    ));
+    assert!(errors[2].starts_with(
+        "Failed to decode api file './tests/validate/system/api-broken.yml' in system folder"
+    ));
+    assert!(errors[2].ends_with("at line 2 column 5"));
+    assert!(errors[3].starts_with(
+        "In 'latency' at line 2 of api file './tests/validate/system/api-defaults.yml'"
+    ));
+    // Rules matching the same route are told apart
+    assert!(errors[4].starts_with(
+        "In rule 'GET /items/:id' at line 6 of api file './tests/validate/system/api-duplicates.yml'"
+    ));
+    assert!(errors[5].starts_with(
+        "In rule 'GET /slow' at line 4 of api file './tests/validate/system/api.yml'"
+    ));
+    assert!(errors[6].starts_with(
+        "In rule 'GET /headers' at line 7 of api file './tests/validate/system/api.yml'"
+    ));
+    assert!(errors[7].starts_with(
+        "Validating api file './tests/validate/system/orders/api.yml' against the shape of api set 'orders'"
+    ));
+}
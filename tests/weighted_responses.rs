@@ -0,0 +1,38 @@
+mod common;
+
+use crate::common::call;
+use axum::http::{Method, StatusCode};
+use axum::Router;
+use mochi::setup_seeded_app;
+
+async fn statuses(app: &Router, count: usize) -> Vec<StatusCode> {
+    let mut statuses = vec![];
+    for _ in 0..count {
+        let (status, _, _) = call(app, Method::GET, "/static/system/orders", &[], "").await;
+        statuses.push(status);
+    }
+    statuses
+}
+
+#[tokio::test]
+async fn weighted_responses() {
+    let app = setup_seeded_app("./tests/weighted_responses".to_string(), None).unwrap();
+
+    let statuses = statuses(&app, 1000).await;
+    let errors = statuses
+        .iter()
+        .filter(|s| **s == StatusCode::INTERNAL_SERVER_ERROR)
+        .count();
+    let oks = statuses.iter().filter(|s| **s == StatusCode::OK).count();
+
+    assert_eq!(errors + oks, 1000);
+    assert!((50..150).contains(&errors), "{errors} errors");
+}
+
+#[tokio::test]
+async fn seeded_weighted_responses_are_reproducible() {
+    let first = setup_seeded_app("./tests/weighted_responses".to_string(), Some(7)).unwrap();
+    let second = setup_seeded_app("./tests/weighted_responses".to_string(), Some(7)).unwrap();
+
+    assert_eq!(statuses(&first, 100).await, statuses(&second, 100).await);
+}